@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::search::TokenizerOptions;
+
+/// Placeholder summary used when no language-specific one matches
+pub const DEFAULT_SUMMARY: &str = "No summary available";
+
+/// Options shared by every paper handled by a `PaperProcessor`.
+///
+/// Defaults preserve the processor's original behavior.
+#[derive(Debug, Clone)]
+pub struct ProcessorConfig {
+    /// Normalization applied to both documents and queries when searching
+    pub tokenizer: TokenizerOptions,
+    /// Indent JSON output; compact output is roughly half the size
+    pub pretty: bool,
+    /// Fail processing on problems that would otherwise only produce warnings
+    pub strict: bool,
+    /// Record how long each paper takes to process
+    pub collect_timings: bool,
+    /// Reject files larger than this many bytes; `None` means no limit
+    pub max_content_bytes: Option<usize>,
+    /// Refuse to process more papers once this many are loaded; `None` means
+    /// no limit
+    pub max_papers: Option<usize>,
+    /// Reject tar archives larger than this many bytes before reading them;
+    /// `None` means no limit
+    pub max_archive_bytes: Option<usize>,
+    /// Expand tabs in line indentation to stops this many columns apart
+    /// before parsing
+    pub tab_width: Option<usize>,
+    /// Convert `:shortcode:` emoji outside code into Unicode characters
+    pub emoji_shortcodes: bool,
+    /// Turn bare `http(s)://` URLs in prose into links
+    pub autolink: bool,
+    /// Wrap rendered tables in `<div class="table-wrapper">`
+    pub wrap_tables: bool,
+    /// Rewrite `http(s)://` link targets through this template, replacing
+    /// `{url}` with the percent-encoded original
+    pub external_link_wrapper: Option<String>,
+    /// Link `@username` outside code through this template, replacing `{user}`
+    pub mention_url: Option<String>,
+    /// Link `#123` outside code through this template, replacing `{number}`
+    pub issue_url: Option<String>,
+    /// Wrap each code block line in `<span class="line" data-line="N">`
+    pub code_line_numbers: bool,
+    /// Highlighting theme passed to a custom code block renderer, one of
+    /// `SYNTAX_THEMES`
+    pub syntax_theme: Option<String>,
+    /// Render `mermaid` fences as `<div class="mermaid">` for mermaid.js
+    pub mermaid: bool,
+    /// Title papers without a frontmatter `title` after their first `#` heading
+    pub title_from_heading: bool,
+    /// Leave a leading `#` heading matching the title out of the rendered body
+    pub strip_title_heading: bool,
+    /// Papers with fewer words are flagged as stubs; 0 disables the check
+    pub stub_word_threshold: usize,
+    /// Truncate abstracts longer than this many characters; `None` means no limit
+    pub abstract_max_chars: Option<usize>,
+    /// Heading level that starts a section, `##` by default
+    pub section_level: u32,
+    /// Lowercased section names left out of the sections map
+    pub excluded_sections: Vec<String>,
+    /// Prefix heading ids as `{prefix}__{id}`, and in-page links and footnotes
+    /// to match; `{slug}` expands to the paper's slug
+    pub heading_id_prefix: Option<String>,
+    /// Alternate frontmatter key names mapped to the names this processor
+    /// reads, such as `keywords` to `tags`
+    pub field_aliases: BTreeMap<String, String>,
+    /// Placeholder summaries keyed by language code, such as `es` or `pt-br`
+    pub default_summaries: BTreeMap<String, String>,
+    /// Frontmatter keys tried in order for `lastUpdated`
+    pub date_keys: Vec<String>,
+    /// Date formats tried in order on each of `date_keys`
+    pub date_formats: Vec<DateFormat>,
+    /// What `lastUpdated` becomes when frontmatter has none
+    pub last_updated_fallback: LastUpdatedFallback,
+    /// How HTML embedded in markdown is rendered
+    pub raw_html: RawHtml,
+    /// Where a paper's `toc` comes from
+    pub toc_source: TocSource,
+    /// Lowercased headings of the section read as a manual table of contents
+    pub toc_headings: Vec<String>,
+    /// How heading ids are made
+    pub heading_id_style: HeadingIdStyle,
+}
+
+/// How `markdown_to_html` gives headings their ids
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeadingIdStyle {
+    /// Lowercased with spaces made `-` and common punctuation dropped, as
+    /// Node.js marked does. Repeated headings share an id.
+    #[default]
+    Kebab,
+    /// As GitHub makes them, with `-1`, `-2`, ... added to repeated ids
+    GitHub,
+    /// No ids
+    None,
+}
+
+impl HeadingIdStyle {
+    /// Parse `kebab`, `github` or `none`
+    pub fn parse(style: &str) -> Option<Self> {
+        match style.trim().to_lowercase().as_str() {
+            "kebab" => Some(HeadingIdStyle::Kebab),
+            "github" => Some(HeadingIdStyle::GitHub),
+            "none" => Some(HeadingIdStyle::None),
+            _ => None,
+        }
+    }
+}
+
+/// Where a paper's `toc` comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TocSource {
+    /// The bold items of a `## Table of Contents` section in the body, or of
+    /// a section with one of the configured `toc_headings`
+    Manual,
+    /// The frontmatter `toc` list
+    Frontmatter,
+    /// The body's section headings, other than the contents section's own
+    Auto,
+    /// The first of these sources that gives a non-empty list
+    Prefer(Vec<TocSource>),
+}
+
+impl Default for TocSource {
+    /// The manual contents section, else the frontmatter list
+    fn default() -> Self {
+        TocSource::Prefer(vec![TocSource::Manual, TocSource::Frontmatter])
+    }
+}
+
+impl TocSource {
+    /// Parse `manual`, `frontmatter` or `auto`, or a comma-separated list of
+    /// them in order of preference such as `manual,auto`
+    pub fn parse(source: &str) -> Option<Self> {
+        let mut sources = source
+            .split(',')
+            .map(|source| match source.trim().to_lowercase().as_str() {
+                "manual" => Some(TocSource::Manual),
+                "frontmatter" => Some(TocSource::Frontmatter),
+                "auto" => Some(TocSource::Auto),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        if sources.len() == 1 {
+            sources.pop()
+        } else {
+            Some(TocSource::Prefer(sources))
+        }
+    }
+}
+
+/// How `markdown_to_html` treats HTML written in a paper's markdown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RawHtml {
+    /// Pass it through unchanged
+    #[default]
+    Allow,
+    /// Clean it against an allowlist, which drops scripting elements such as
+    /// `<script>` and `<iframe>`, event handler attributes and `javascript:`
+    /// URLs
+    Sanitize,
+    /// Show it as text
+    Escape,
+}
+
+impl RawHtml {
+    /// Parse `allow`, `sanitize` or `escape`
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode.trim().to_lowercase().as_str() {
+            "allow" => Some(RawHtml::Allow),
+            "sanitize" => Some(RawHtml::Sanitize),
+            "escape" => Some(RawHtml::Escape),
+            _ => None,
+        }
+    }
+}
+
+/// A way of writing dates in frontmatter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateFormat {
+    /// ISO 8601 dates and date-times such as `2024-03-01T12:30:00Z`
+    Iso,
+    /// Day first, as in Europe: `01/03/2024`, `01.03.2024` or `01-03-2024`
+    Dmy,
+    /// Month first, as in the US: `03/01/2024`
+    Mdy,
+}
+
+impl DateFormat {
+    /// Parse `iso`, `dmy` or `mdy`, or a comma-separated list of them in the
+    /// order to try them
+    pub fn parse_list(formats: &str) -> Option<Vec<Self>> {
+        formats
+            .split(',')
+            .map(|format| match format.trim().to_lowercase().as_str() {
+                "iso" => Some(DateFormat::Iso),
+                "dmy" => Some(DateFormat::Dmy),
+                "mdy" => Some(DateFormat::Mdy),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Where a paper's `lastUpdated` comes from when frontmatter omits it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LastUpdatedFallback {
+    /// The time the paper was processed. This changes on every build.
+    #[default]
+    Now,
+    /// The file's modification time, when one was passed in; otherwise the
+    /// time the paper was processed
+    Mtime,
+    /// An empty string
+    Empty,
+    /// The Unix epoch, `1970-01-01T00:00:00.000Z`
+    Epoch,
+}
+
+impl LastUpdatedFallback {
+    /// Parse `now`, `mtime`, `empty` or `epoch`
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode.trim().to_lowercase().as_str() {
+            "now" => Some(LastUpdatedFallback::Now),
+            "mtime" => Some(LastUpdatedFallback::Mtime),
+            "empty" => Some(LastUpdatedFallback::Empty),
+            "epoch" => Some(LastUpdatedFallback::Epoch),
+            _ => None,
+        }
+    }
+}
+
+impl Default for ProcessorConfig {
+    fn default() -> Self {
+        ProcessorConfig {
+            tokenizer: TokenizerOptions::default(),
+            pretty: true,
+            strict: false,
+            collect_timings: false,
+            max_content_bytes: None,
+            max_papers: None,
+            max_archive_bytes: None,
+            tab_width: None,
+            emoji_shortcodes: false,
+            autolink: false,
+            wrap_tables: false,
+            external_link_wrapper: None,
+            mention_url: None,
+            issue_url: None,
+            code_line_numbers: false,
+            syntax_theme: None,
+            mermaid: false,
+            title_from_heading: true,
+            strip_title_heading: false,
+            stub_word_threshold: 0,
+            abstract_max_chars: None,
+            section_level: 2,
+            excluded_sections: Vec::new(),
+            heading_id_prefix: None,
+            field_aliases: BTreeMap::new(),
+            default_summaries: BTreeMap::new(),
+            date_keys: vec!["lastUpdated".to_string()],
+            date_formats: vec![DateFormat::Iso],
+            last_updated_fallback: LastUpdatedFallback::Now,
+            raw_html: RawHtml::Allow,
+            toc_source: TocSource::default(),
+            toc_headings: vec!["table of contents".to_string()],
+            heading_id_style: HeadingIdStyle::Kebab,
+        }
+    }
+}
+
+impl ProcessorConfig {
+    /// The placeholder summary for a paper in `lang`, trying the full code,
+    /// then its primary subtag (`es` for `es-MX`), then English
+    pub fn default_summary(&self, lang: Option<&str>) -> &str {
+        let lang = lang.map(|lang| lang.trim().to_lowercase());
+        let primary = lang
+            .as_deref()
+            .map(|lang| lang.split(['-', '_']).next().unwrap_or(lang));
+
+        for key in [lang.as_deref(), primary, Some("en")].into_iter().flatten() {
+            if let Some(summary) = self.default_summaries.get(key) {
+                return summary;
+            }
+        }
+        DEFAULT_SUMMARY
+    }
+}
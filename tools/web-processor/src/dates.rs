@@ -0,0 +1,234 @@
+/// A calendar date with optional time of day, as written in frontmatter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// Offset from UTC in minutes; dates without a zone are treated as UTC
+    pub offset_minutes: i32,
+}
+
+impl DateTime {
+    /// Seconds since the Unix epoch, adjusted to UTC.
+    pub fn unix_seconds(&self) -> i64 {
+        let days = days_from_civil(self.year as i64, self.month as i64, self.day as i64);
+        days * 86_400 + (self.hour * 3600 + self.minute * 60 + self.second) as i64
+            - self.offset_minutes as i64 * 60
+    }
+}
+
+/// Parse an ISO 8601 date (`2024-03-01`) or date-time
+/// (`2024-03-01T12:30:00Z`, `2024-03-01 12:30`, `2024-03-01T12:30:00.250+02:00`).
+pub fn parse_date(input: &str) -> Option<DateTime> {
+    let input = input.trim();
+    let (date, rest) = match input.find(['T', 't', ' ']) {
+        Some(idx) => (&input[..idx], Some(&input[idx + 1..])),
+        None => (input, None),
+    };
+
+    let mut parts = date.splitn(3, '-');
+    let year: i32 = parse_digits(parts.next()?, 4)?;
+    let month: u32 = parse_digits(parts.next()?, 2)?;
+    let day: u32 = parse_digits(parts.next()?, 2)?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let mut parsed = DateTime {
+        year,
+        month,
+        day,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        offset_minutes: 0,
+    };
+
+    if let Some(rest) = rest {
+        let rest = rest.trim();
+        let (time, zone) = match rest.find(['Z', 'z', '+', '-']) {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, ""),
+        };
+
+        let time = time.split('.').next()?;
+        let mut fields = time.split(':');
+        parsed.hour = parse_digits(fields.next()?, 2)?;
+        parsed.minute = parse_digits(fields.next()?, 2)?;
+        parsed.second = match fields.next() {
+            Some(second) => parse_digits(second, 2)?,
+            None => 0,
+        };
+        if fields.next().is_some() || parsed.hour > 23 || parsed.minute > 59 || parsed.second > 60 {
+            return None;
+        }
+
+        parsed.offset_minutes = parse_offset(zone.trim())?;
+    }
+
+    Some(parsed)
+}
+
+/// Parse a numeric `DD/MM/YYYY` date, or `MM/DD/YYYY` when `!day_first`.
+/// `.` and `-` also separate the parts, and day and month may be one digit.
+pub fn parse_numeric_date(input: &str, day_first: bool) -> Option<DateTime> {
+    let mut parts = input.trim().split(['/', '.', '-']);
+    let first: u32 = parse_short(parts.next()?)?;
+    let second: u32 = parse_short(parts.next()?)?;
+    let year: i32 = parse_digits(parts.next()?, 4)?;
+    if parts.next().is_some() {
+        return None;
+    }
+    let (day, month) = if day_first {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    Some(DateTime {
+        year,
+        month,
+        day,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        offset_minutes: 0,
+    })
+}
+
+/// Format a date's calendar day as `YYYY-MM-DD`.
+pub fn format_date(date: &DateTime) -> String {
+    format!("{:04}-{:02}-{:02}", date.year, date.month, date.day)
+}
+
+/// Format milliseconds since the Unix epoch as `YYYY-MM-DDTHH:MM:SS.sssZ`.
+pub fn format_iso_millis(unix_millis: i64) -> String {
+    let seconds = unix_millis.div_euclid(1000);
+    let millis = unix_millis.rem_euclid(1000);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let time = seconds.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60,
+        millis
+    )
+}
+
+/// Format seconds since the Unix epoch as an RFC 2822 date in UTC, as used by
+/// RSS: `Fri, 01 Mar 2024 12:30:00 +0000`.
+pub fn format_rfc2822(unix_seconds: i64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let days = unix_seconds.div_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    let time = unix_seconds.rem_euclid(86_400);
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} +0000",
+        WEEKDAYS[days.rem_euclid(7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Format seconds since the Unix epoch as an RFC 3339 date-time in UTC:
+/// `2024-03-01T12:30:00Z`.
+pub fn format_rfc3339(unix_seconds: i64) -> String {
+    let (year, month, day) = civil_from_days(unix_seconds.div_euclid(86_400));
+    let time = unix_seconds.rem_euclid(86_400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Convert a civil date into days since 1970-01-01.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Convert days since 1970-01-01 into a `(year, month, day)` civil date.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    // Howard Hinnant's days_from_civil, inverted
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn parse_offset(zone: &str) -> Option<i32> {
+    if zone.is_empty() || zone.eq_ignore_ascii_case("z") {
+        return Some(0);
+    }
+
+    let sign = match zone.as_bytes()[0] {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let zone = zone[1..].replace(':', "");
+    if zone.len() != 4 {
+        return None;
+    }
+    let hours: i32 = parse_digits(&zone[..2], 2)?;
+    let minutes: i32 = parse_digits(&zone[2..], 2)?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+fn parse_digits<T: std::str::FromStr>(digits: &str, len: usize) -> Option<T> {
+    if digits.len() != len || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// One or two digits
+fn parse_short<T: std::str::FromStr>(digits: &str) -> Option<T> {
+    parse_digits(digits, 1).or_else(|| parse_digits(digits, 2))
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt;
+
+use regex::{Captures, Regex};
+
+use crate::BrokenLink;
+
+/// Why a paper could not be processed, or a check on the processor failed.
+///
+/// Outside strict mode most of these are recorded as warnings on the paper
+/// instead of being returned.
+#[derive(Debug)]
+pub enum ProcessError {
+    /// The content has no `---` delimited frontmatter block
+    MissingFrontmatter,
+    /// The frontmatter has no `title` and none was taken from a `#` heading
+    MissingTitle(String),
+    /// A top-level frontmatter key appears more than once
+    DuplicateKey(String),
+    /// `lastUpdated` is not an ISO 8601 date
+    InvalidDate(String),
+    /// `authors` is not a list of names or `{name, affiliation}` objects
+    MalformedAuthors(String),
+    /// The frontmatter is not valid YAML. `line` is where the problem is in
+    /// the whole file, counting the opening `---` as line 1.
+    Yaml {
+        error: serde_yaml::Error,
+        line: Option<usize>,
+    },
+    /// The frontmatter `slug` is already used by the paper from `existing`
+    DuplicateSlug {
+        slug: String,
+        filename: String,
+        existing: String,
+    },
+    /// The file exceeds the configured `max_content_bytes`
+    TooLarge { size: usize, limit: usize },
+    /// The configured `max_papers` are already loaded
+    CapacityExceeded { limit: usize },
+    /// A configuration value was not one of the accepted ones
+    InvalidConfig(String),
+    /// Links that point at no paper or heading, from `assert_no_broken_links`
+    BrokenLinks(Vec<BrokenLink>),
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessError::MissingFrontmatter => write!(
+                f,
+                "No frontmatter found in content. This will cause issues in the web interface."
+            ),
+            ProcessError::MissingTitle(filename) => {
+                write!(f, "Paper {} is missing a title in frontmatter!", filename)
+            }
+            ProcessError::DuplicateKey(key) => {
+                write!(f, "Duplicate frontmatter key `{}`", key)
+            }
+            ProcessError::InvalidDate(date) => {
+                write!(f, "Could not parse lastUpdated date `{}`", date)
+            }
+            ProcessError::MalformedAuthors(reason) => write!(f, "Malformed authors: {}", reason),
+            ProcessError::Yaml { error, line } => {
+                // serde_yaml counts lines from the start of the frontmatter;
+                // shift every position it mentions to match the file
+                let message = error.to_string();
+                let offset = match (line, error.location()) {
+                    (Some(line), Some(location)) => line.saturating_sub(location.line()),
+                    _ => 0,
+                };
+                let message =
+                    Regex::new(r"line (\d+)")
+                        .unwrap()
+                        .replace_all(&message, |c: &Captures| {
+                            let line: usize = c[1].parse().unwrap_or(0);
+                            format!("line {}", line + offset)
+                        });
+                write!(f, "Invalid frontmatter YAML: {}", message)
+            }
+            ProcessError::DuplicateSlug {
+                slug,
+                filename,
+                existing,
+            } => write!(
+                f,
+                "Slug `{}` in {} is already used by {}",
+                slug, filename, existing
+            ),
+            ProcessError::TooLarge { size, limit } => write!(
+                f,
+                "Content is {} bytes, exceeding the {} byte limit",
+                size, limit
+            ),
+            ProcessError::CapacityExceeded { limit } => {
+                write!(f, "Cannot hold more than {} papers", limit)
+            }
+            ProcessError::InvalidConfig(message) => write!(f, "{}", message),
+            ProcessError::BrokenLinks(broken) => {
+                write!(f, "Found {} broken link(s):", broken.len())?;
+                for link in broken {
+                    write!(f, "\n  {}: {}", link.slug, link.link)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProcessError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ProcessError::Yaml { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl ProcessError {
+    /// A YAML error in frontmatter whose first line is line `first_line` of
+    /// the file
+    pub fn yaml(error: serde_yaml::Error, first_line: usize) -> Self {
+        let line = error
+            .location()
+            .map(|location| location.line() + first_line - 1);
+        ProcessError::Yaml { error, line }
+    }
+}
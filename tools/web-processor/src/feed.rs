@@ -0,0 +1,141 @@
+use std::cmp::Reverse;
+
+use crate::dates;
+use crate::Paper;
+
+/// Papers to include in a feed: drafts dropped, newest first. Papers whose
+/// `lastUpdated` cannot be parsed sort after all dated ones.
+pub fn entries<'a>(papers: impl IntoIterator<Item = &'a Paper>) -> Vec<&'a Paper> {
+    let mut entries: Vec<(&Paper, Option<i64>)> = papers
+        .into_iter()
+        .filter(|paper| !paper.draft)
+        .map(|paper| (paper, updated(paper)))
+        .collect();
+    entries.sort_by_key(|&(_, updated)| Reverse(updated));
+    entries.into_iter().map(|(paper, _)| paper).collect()
+}
+
+/// Render an RSS 2.0 document with one `<item>` per paper, in the order given.
+pub fn rss(papers: &[&Paper], base_url: &str, channel_title: &str) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\">\n<channel>\n");
+    xml.push_str(&format!("<title>{}</title>\n", escape(channel_title)));
+    xml.push_str(&format!("<link>{}/</link>\n", escape(base_url)));
+    xml.push_str(&format!(
+        "<description>{}</description>\n",
+        escape(channel_title)
+    ));
+    if let Some(newest) = papers.iter().find_map(|paper| updated(paper)) {
+        xml.push_str(&format!(
+            "<lastBuildDate>{}</lastBuildDate>\n",
+            dates::format_rfc2822(newest)
+        ));
+    }
+    for paper in papers {
+        xml.push_str(&rss_item(paper, base_url));
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+/// Render an Atom 1.0 document with one `<entry>` per paper, in the order
+/// given. The feed is updated when its first dated entry was, or at the Unix
+/// epoch if none are dated, and so are undated entries.
+pub fn atom(papers: &[&Paper], base_url: &str, feed_title: &str) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let newest = papers.iter().find_map(|paper| updated(paper)).unwrap_or(0);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("<id>{}/</id>\n", escape(base_url)));
+    xml.push_str(&format!("<title>{}</title>\n", escape(feed_title)));
+    xml.push_str(&format!(
+        "<updated>{}</updated>\n",
+        dates::format_rfc3339(newest)
+    ));
+    xml.push_str(&format!("<link href=\"{}/\"/>\n", escape(base_url)));
+    // Atom needs an author for entries that name none
+    xml.push_str(&format!(
+        "<author><name>{}</name></author>\n",
+        escape(feed_title)
+    ));
+    for paper in papers {
+        xml.push_str(&atom_entry(paper, base_url));
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// A single `<entry>`, identified by and linking to the paper's `paper_url`
+fn atom_entry(paper: &Paper, base_url: &str) -> String {
+    let link = escape(&paper_url(base_url, &paper.slug));
+    let mut entry = String::from("<entry>\n");
+    entry.push_str(&format!("<id>{}</id>\n", link));
+    entry.push_str(&format!("<title>{}</title>\n", escape(&paper.title)));
+    entry.push_str(&format!(
+        "<updated>{}</updated>\n",
+        dates::format_rfc3339(updated(paper).unwrap_or(0))
+    ));
+    entry.push_str(&format!("<link rel=\"alternate\" href=\"{}\"/>\n", link));
+    entry.push_str(&format!("<summary>{}</summary>\n", escape(&paper.summary)));
+    for author in &paper.authors {
+        entry.push_str(&format!(
+            "<author><name>{}</name></author>\n",
+            escape(&author.name)
+        ));
+    }
+    for tag in paper.tags.iter().flatten() {
+        entry.push_str(&format!("<category term=\"{}\"/>\n", escape(tag)));
+    }
+    entry.push_str("</entry>\n");
+    entry
+}
+
+/// The public URL of a paper: `{base_url}/papers/{slug}`
+pub fn paper_url(base_url: &str, slug: &str) -> String {
+    format!("{}/papers/{}", base_url.trim_end_matches('/'), slug)
+}
+
+/// A single `<item>`, linking to the paper's `paper_url`
+fn rss_item(paper: &Paper, base_url: &str) -> String {
+    let link = escape(&paper_url(base_url, &paper.slug));
+    let mut item = String::from("<item>\n");
+    item.push_str(&format!("<title>{}</title>\n", escape(&paper.title)));
+    item.push_str(&format!("<link>{}</link>\n", link));
+    item.push_str(&format!("<guid isPermaLink=\"true\">{}</guid>\n", link));
+    item.push_str(&format!(
+        "<description>{}</description>\n",
+        escape(&paper.summary)
+    ));
+    if let Some(seconds) = updated(paper) {
+        item.push_str(&format!(
+            "<pubDate>{}</pubDate>\n",
+            dates::format_rfc2822(seconds)
+        ));
+    }
+    for tag in paper.tags.iter().flatten() {
+        item.push_str(&format!("<category>{}</category>\n", escape(tag)));
+    }
+    item.push_str("</item>\n");
+    item
+}
+
+fn updated(paper: &Paper) -> Option<i64> {
+    dates::parse_date(&paper.last_updated).map(|date| date.unix_seconds())
+}
+
+/// Escape text for use in XML or HTML element content and attribute values
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,127 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_yaml::{Mapping, Value};
+
+/// Top-level frontmatter entries in document order, duplicates included.
+struct Entries(Vec<(Value, Value)>);
+
+impl<'de> Deserialize<'de> for Entries {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor;
+
+        impl<'de> Visitor<'de> for EntriesVisitor {
+            type Value = Entries;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a mapping of frontmatter keys")
+            }
+
+            fn visit_unit<E>(self) -> Result<Entries, E> {
+                Ok(Entries(Vec::new()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Entries, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(key) = map.next_key::<Value>()? {
+                    entries.push((key, map.next_value::<Value>()?));
+                }
+                Ok(Entries(entries))
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor)
+    }
+}
+
+/// Deserialize a list of strings written either as a YAML sequence or as one
+/// comma-separated string such as `tags: machine-learning, nlp`
+pub fn string_or_list<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error> {
+    struct StringOrListVisitor;
+
+    impl<'de> Visitor<'de> for StringOrListVisitor {
+        type Value = Option<Vec<String>>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a list of strings or a comma-separated string")
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_none<E>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            deserializer.deserialize_any(self)
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E> {
+            Ok(Some(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(String::from)
+                    .collect(),
+            ))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut items = Vec::new();
+            while let Some(item) = seq.next_element::<String>()? {
+                items.push(item);
+            }
+            Ok(Some(items))
+        }
+    }
+
+    deserializer.deserialize_any(StringOrListVisitor)
+}
+
+/// Parse frontmatter YAML into a mapping, keeping the last value of any
+/// repeated top-level key. Returns the mapping and the repeated key names.
+pub fn parse_mapping(yaml: &str) -> Result<(Mapping, Vec<String>), serde_yaml::Error> {
+    let Entries(entries) = serde_yaml::from_str(yaml)?;
+    let mut mapping = Mapping::new();
+    let mut duplicates = Vec::new();
+
+    for (key, value) in entries {
+        if mapping.contains_key(&key) {
+            let name = match &key {
+                Value::String(name) => name.clone(),
+                other => serde_yaml::to_string(other)
+                    .map(|s| s.trim().to_string())
+                    .unwrap_or_default(),
+            };
+            if !duplicates.contains(&name) {
+                duplicates.push(name);
+            }
+        }
+        mapping.insert(key, value);
+    }
+
+    Ok((mapping, duplicates))
+}
+
+/// Rename top-level keys found in `aliases` (alternate name to canonical
+/// name). A key is left alone when its canonical name is also present.
+pub fn apply_aliases(mapping: &mut Mapping, aliases: &BTreeMap<String, String>) {
+    for (alias, canonical) in aliases {
+        let canonical = Value::String(canonical.clone());
+        if mapping.contains_key(&canonical) {
+            continue;
+        }
+        if let Some(value) = mapping.remove(alias.as_str()) {
+            mapping.insert(canonical, value);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use regex::{Captures, Regex};
+
+/// Expand `{{include: slug#section}}` directives in `markdown`, recursively.
+///
+/// `lookup` returns the raw markdown of a paper's section, given the slug and
+/// lowercased section name. A directive that names a missing section, or that
+/// would include a section already being expanded, is left as written and
+/// reported in `problems`.
+///
+/// Footnote labels in an included section become `{slug}-{label}`, so they
+/// cannot collide with the including paper's own footnotes. References to
+/// footnotes defined outside the section are reported, as they will not
+/// resolve.
+pub fn expand<'a>(
+    markdown: &str,
+    lookup: &dyn Fn(&str, &str) -> Option<&'a str>,
+    problems: &mut Vec<String>,
+) -> String {
+    let directive = Regex::new(r"\{\{\s*include:\s*([^#}\s]+)#([^}]+?)\s*\}\}").unwrap();
+    expand_with(markdown, &directive, lookup, &mut Vec::new(), problems)
+}
+
+fn expand_with<'a>(
+    markdown: &str,
+    directive: &Regex,
+    lookup: &dyn Fn(&str, &str) -> Option<&'a str>,
+    stack: &mut Vec<String>,
+    problems: &mut Vec<String>,
+) -> String {
+    directive
+        .replace_all(markdown, |captures: &Captures| {
+            let slug = &captures[1];
+            let section = captures[2].trim().to_lowercase();
+            let target = format!("{}#{}", slug, section);
+
+            if stack.contains(&target) {
+                problems.push(format!(
+                    "Include cycle: {} -> {}",
+                    stack.join(" -> "),
+                    target
+                ));
+                return captures[0].to_string();
+            }
+            let Some(text) = lookup(slug, &section) else {
+                problems.push(format!("Included section {} does not exist", target));
+                return captures[0].to_string();
+            };
+
+            stack.push(target.clone());
+            let expanded = expand_with(text, directive, lookup, stack, problems);
+            stack.pop();
+            namespace_footnotes(&expanded, slug, &target, problems)
+        })
+        .into_owned()
+}
+
+/// `text` with every `[^label]` footnote reference and definition renamed
+/// `[^{slug}-{label}]`
+fn namespace_footnotes(text: &str, slug: &str, target: &str, problems: &mut Vec<String>) -> String {
+    let footnote = Regex::new(r"\[\^([^\]\s]+)\]").unwrap();
+    let definition = Regex::new(r"(?m)^ {0,3}\[\^([^\]\s]+)\]:").unwrap();
+
+    let defined: Vec<&str> = definition
+        .captures_iter(text)
+        .map(|captures| captures.get(1).unwrap().as_str())
+        .collect();
+    let mut undefined: Vec<&str> = Vec::new();
+    for captures in footnote.captures_iter(text) {
+        let label = captures.get(1).unwrap().as_str();
+        if !defined.contains(&label) && !undefined.contains(&label) {
+            undefined.push(label);
+        }
+    }
+    for label in undefined {
+        problems.push(format!(
+            "Included section {} uses footnote [^{}], defined outside it",
+            target, label
+        ));
+    }
+
+    footnote
+        .replace_all(text, |captures: &Captures| {
+            format!("[^{}-{}]", slug, &captures[1])
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_from(markdown: &str, section: &'static str) -> (String, Vec<String>) {
+        let lookup =
+            move |slug: &str, name: &str| (slug == "b" && name == "notes").then_some(section);
+        let mut problems = Vec::new();
+        (expand(markdown, &lookup, &mut problems), problems)
+    }
+
+    #[test]
+    fn included_footnotes_are_namespaced() {
+        let (expanded, problems) = expand_from(
+            "Own[^1]\n\n{{include: b#notes}}\n\n[^1]: Mine\n",
+            "Theirs[^1]\n\n[^1]: From b\n",
+        );
+        assert_eq!(
+            expanded,
+            "Own[^1]\n\nTheirs[^b-1]\n\n[^b-1]: From b\n\n\n[^1]: Mine\n"
+        );
+        assert!(problems.is_empty());
+    }
+
+    #[test]
+    fn footnotes_defined_outside_the_section_are_reported() {
+        let (expanded, problems) = expand_from("{{include: b#notes}}\n", "Theirs[^2]\n");
+        assert_eq!(expanded, "Theirs[^b-2]\n\n");
+        assert_eq!(
+            problems,
+            ["Included section b#notes uses footnote [^2], defined outside it"]
+        );
+    }
+}
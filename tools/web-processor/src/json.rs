@@ -0,0 +1,97 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use serde::ser::{SerializeSeq, Serializer};
+use serde::Serialize;
+use serde_json::ser::{CompactFormatter, Formatter, PrettyFormatter};
+
+/// Serialize `value` as indented or compact JSON.
+pub fn to_string<T: Serialize + ?Sized>(value: &T, pretty: bool) -> serde_json::Result<String> {
+    if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+}
+
+/// Gzip-compress `bytes` at level 9 (`Compression::best()`).
+///
+/// The payloads are compressed once at build time and served many times, so
+/// the smallest output is worth the extra CPU.
+pub fn gzip(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+/// Writer that buffers serializer output until it is taken as a chunk.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.0.borrow_mut());
+        // serde_json only ever writes valid UTF-8
+        String::from_utf8(bytes).unwrap_or_default()
+    }
+}
+
+impl io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Serialize `items` as a JSON array, handing the output to `emit` one element
+/// at a time. The concatenated chunks are identical to `to_string(items, pretty)`.
+/// Serialization failures are passed through `map_err` so they share the error
+/// type returned by `emit`.
+pub fn write_array_chunked<T, E>(
+    items: &[T],
+    pretty: bool,
+    emit: impl FnMut(String) -> Result<(), E>,
+    map_err: impl Fn(serde_json::Error) -> E,
+) -> Result<(), E>
+where
+    T: Serialize,
+{
+    if pretty {
+        write_chunked_with(items, PrettyFormatter::new(), emit, map_err)
+    } else {
+        write_chunked_with(items, CompactFormatter, emit, map_err)
+    }
+}
+
+fn write_chunked_with<T, E, F>(
+    items: &[T],
+    formatter: F,
+    mut emit: impl FnMut(String) -> Result<(), E>,
+    map_err: impl Fn(serde_json::Error) -> E,
+) -> Result<(), E>
+where
+    T: Serialize,
+    F: Formatter,
+{
+    let buffer = SharedBuffer::default();
+    let mut serializer = serde_json::Serializer::with_formatter(buffer.clone(), formatter);
+    let mut seq = serializer
+        .serialize_seq(Some(items.len()))
+        .map_err(&map_err)?;
+
+    for item in items {
+        seq.serialize_element(item).map_err(&map_err)?;
+        emit(buffer.take())?;
+    }
+
+    seq.end().map_err(&map_err)?;
+    emit(buffer.take())
+}
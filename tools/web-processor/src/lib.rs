@@ -1,16 +1,49 @@
-use pulldown_cmark::{html, Options, Parser};
+use pulldown_cmark::{html, Parser};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use wasm_bindgen::prelude::*;
 
+mod config;
+mod dates;
+mod error;
+mod feed;
+mod frontmatter;
+mod includes;
+mod json;
+mod links;
+mod meta;
+mod render;
+mod search;
+
+pub use config::{
+    DateFormat, HeadingIdStyle, LastUpdatedFallback, ProcessorConfig, RawHtml, TocSource,
+};
+pub use error::ProcessError;
+pub use links::BrokenLink;
+use render::{BodyEvents, Rendered};
+pub use render::{
+    CodeBlockRenderer, DefaultCodeBlockRenderer, Figure, Heading, NumberedCodeBlockRenderer,
+    SYNTAX_THEMES,
+};
+use search::SearchIndex;
+pub use search::{SearchHit, TermCount, TokenizerOptions};
+
 // Import the `console.log` function from the browser
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
 }
 
+// Native builds have no console, so log to stderr instead
+#[cfg(not(target_arch = "wasm32"))]
+fn log(s: &str) {
+    eprintln!("{}", s);
+}
+
 // Define a macro for logging
 macro_rules! console_log {
     ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
@@ -18,24 +51,84 @@ macro_rules! console_log {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Author {
-    name: String,
-    affiliation: Option<String>,
+    pub name: String,
+    pub affiliation: Option<String>,
+    /// How the author sorts in the author index, such as `Doe, Jane`
+    #[serde(rename = "sortName", default, skip_serializing_if = "Option::is_none")]
+    pub sort_name: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl Author {
+    /// The explicit `sortName`, or else the name rearranged surname first:
+    /// `Jane Doe` sorts as `Doe, Jane`. Names already written as
+    /// `Doe, Jane` and single names are used as they are.
+    pub fn sort_key(&self) -> String {
+        if let Some(ref sort_name) = self.sort_name {
+            return sort_name.clone();
+        }
+
+        let name = self.name.trim();
+        if name.contains(',') {
+            return name.to_string();
+        }
+
+        let mut words: Vec<&str> = name.split_whitespace().collect();
+        let suffix = match words.last() {
+            Some(&last) if words.len() > 2 && is_name_suffix(last) => words.pop(),
+            _ => None,
+        };
+        match words.split_last() {
+            Some((surname, given)) if !given.is_empty() => {
+                let mut key = format!("{}, {}", surname, given.join(" "));
+                if let Some(suffix) = suffix {
+                    key.push_str(&format!(", {}", suffix));
+                }
+                key
+            }
+            _ => name.to_string(),
+        }
+    }
+}
+
+/// An author with the papers they wrote
+#[derive(Debug, Serialize, Clone)]
+pub struct AuthorEntry {
+    pub name: String,
+    #[serde(rename = "sortName")]
+    pub sort_name: String,
+    pub affiliation: Option<String>,
+    /// Slugs of the author's papers, in processing order
+    pub papers: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct PaperMetadata {
     title: Option<String>,
-    authors: Option<Vec<Author>>,
+    authors: Option<serde_yaml::Value>,
+    #[serde(default, deserialize_with = "frontmatter::string_or_list")]
     tags: Option<Vec<String>>,
     status: Option<String>,
     #[serde(rename = "lastUpdated")]
     last_updated: Option<String>,
     toc: Option<Vec<String>>,
+    cover: Option<String>,
+    draft: Option<bool>,
+    series: Option<String>,
+    #[serde(alias = "seriesOrder")]
+    series_order: Option<i64>,
+    canonical: Option<String>,
+    hidden: Option<bool>,
+    noindex: Option<bool>,
+    slug: Option<String>,
+    layout: Option<String>,
+    classes: Option<Vec<String>>,
+    redirect: Option<String>,
+    summary: Option<String>,
     #[serde(flatten)]
-    extra: HashMap<String, serde_json::Value>,
+    extra: BTreeMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Paper {
     pub title: String,
     pub slug: String,
@@ -43,18 +136,324 @@ pub struct Paper {
     pub summary: String,
     #[serde(rename = "abstract")]
     pub abstract_text: String,
+    /// The first sentence of the abstract, else of the summary or the body, as plain text of at most 155 characters
+    #[serde(rename = "metaDescription")]
+    pub meta_description: String,
+    /// Whether `abstract` was cut to the configured length
+    #[serde(rename = "abstractTruncated")]
+    pub abstract_truncated: bool,
+    /// The whole abstract, present only when `abstract` was truncated
+    #[serde(rename = "abstractFull", skip_serializing_if = "Option::is_none")]
+    pub abstract_full: Option<String>,
     pub toc: Vec<String>,
+    /// The frontmatter `toc`, kept so `rerender_all` can choose `toc` again
+    #[serde(rename = "tocFrontmatter", skip_serializing_if = "Option::is_none")]
+    pub toc_frontmatter: Option<Vec<String>>,
+    /// Every heading in the body, with the id used in `html`
+    pub headings: Vec<Heading>,
+    /// Captioned images in the body, numbered in document order
+    #[serde(default)]
+    pub figures: Vec<Figure>,
     pub content: String,
     pub html: String,
     #[serde(rename = "lastUpdated")]
     pub last_updated: String,
+    /// The frontmatter key and format `lastUpdated` was read with, absent
+    /// when no date could be read
+    #[serde(rename = "lastUpdatedSource", skip_serializing_if = "Option::is_none")]
+    pub last_updated_source: Option<DateSource>,
     pub authors: Vec<Author>,
     pub tags: Option<Vec<String>>,
+    /// Tags as written in frontmatter, present only when normalization changed them
+    #[serde(rename = "tagsRaw", skip_serializing_if = "Option::is_none")]
+    pub tags_raw: Option<Vec<String>>,
     pub status: Option<String>,
+    /// Whether the body uses `$`/`$$` math, so MathJax can be skipped otherwise
+    #[serde(rename = "hasMath")]
+    pub has_math: bool,
+    /// Whether the body has fenced code blocks, so Prism can be skipped otherwise
+    #[serde(rename = "hasCode")]
+    pub has_code: bool,
+    /// Checked task list items in the body
+    #[serde(rename = "tasksDone")]
+    pub tasks_done: usize,
+    /// All task list items in the body
+    #[serde(rename = "tasksTotal")]
+    pub tasks_total: usize,
+    /// Words of prose in the body, leaving out code blocks and markup
+    #[serde(rename = "wordCount")]
+    pub word_count: usize,
+    /// Estimated minutes to read `wordCount` words at 200 words a minute
+    #[serde(rename = "readingMinutes")]
+    pub reading_minutes: usize,
+    /// Whether the body has fewer words than the configured stub threshold
+    #[serde(rename = "isStub")]
+    pub is_stub: bool,
+    /// The `cover` frontmatter image, or else the first image in the body
+    #[serde(rename = "coverImage")]
+    pub cover_image: Option<String>,
+    /// Where the paper was first published, from `canonical` in frontmatter
+    #[serde(rename = "canonicalUrl", skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<String>,
+    /// Where the paper's page should redirect, from `redirect` in
+    /// frontmatter. Redirecting papers are listed but their body is not
+    /// rendered, so `html` is empty.
+    #[serde(rename = "redirectUrl", skip_serializing_if = "Option::is_none")]
+    pub redirect_url: Option<String>,
+    /// A page layout hint such as `wide`, from `layout` in frontmatter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    /// CSS classes for the article wrapper, from `classes` in frontmatter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classes: Option<Vec<String>>,
+    /// Whether the file started with a `---` frontmatter block, even one
+    /// whose YAML could not be read
+    #[serde(rename = "hadFrontmatter")]
+    pub had_frontmatter: bool,
+    /// Set by `hidden: true` in frontmatter; hidden papers are left out of
+    /// listings, feeds and search but can still be fetched by slug
+    pub hidden: bool,
+    /// Set by `noindex: true` in frontmatter; the page's meta tags ask search
+    /// engines not to index it, but it is still listed
+    #[serde(default)]
+    pub noindex: bool,
+    /// Set by `draft: true` in frontmatter; drafts are left out of feeds
+    pub draft: bool,
+    /// The multi-part series the paper belongs to, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub series: Option<String>,
+    /// Position within `series`, from `series_order` in frontmatter
+    #[serde(rename = "seriesOrder", skip_serializing_if = "Option::is_none")]
+    pub series_order: Option<i64>,
+    /// Hex FNV-1a hash of the filename and source file, frontmatter included
+    #[serde(rename = "contentHash")]
+    pub content_hash: String,
+    /// Raw markdown of each `##` section, keyed by lowercased heading. Only
+    /// available through `get_sections_json`.
+    #[serde(skip)]
+    pub sections: BTreeMap<String, String>,
+    /// Keys of `sections` in the order their headings appear in the document
+    #[serde(rename = "sectionNames")]
+    pub section_names: Vec<String>,
+    /// Problems noticed while processing that did not prevent it
+    pub warnings: Vec<String>,
+    /// Those of `warnings` from frontmatter and includes, which re-rendering
+    /// keeps as they are
+    #[serde(skip)]
+    pub source_warnings: Vec<String>,
+    /// Those of `warnings` about the rendered body, which `rerender_all`
+    /// recomputes
+    #[serde(
+        rename = "renderWarnings",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub render_warnings: Vec<String>,
+    /// Those of `warnings` that would have failed processing in strict mode
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
     #[serde(flatten)]
-    pub extra: HashMap<String, serde_json::Value>,
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl Paper {
+    /// Replace everything derived from rendering the body
+    fn set_rendered(&mut self, rendered: Rendered) {
+        self.html = rendered.html;
+        self.headings = rendered.headings;
+        self.has_math = rendered.stats.has_math;
+        self.has_code = rendered.stats.has_code;
+        self.tasks_done = rendered.stats.tasks_done;
+        self.tasks_total = rendered.stats.tasks_total;
+        self.word_count = rendered.stats.word_count;
+        self.reading_minutes = reading_minutes(rendered.stats.word_count);
+        self.figures = rendered.stats.figures;
+    }
+
+    /// Replace everything `rerender_all` and `resolve_includes` recompute
+    fn set_rerendered(&mut self, rerendered: Rerendered) {
+        self.set_rendered(rerendered.rendered);
+        self.is_stub = rerendered.is_stub;
+        self.warnings = self
+            .source_warnings
+            .iter()
+            .chain(&rerendered.render_warnings)
+            .cloned()
+            .collect();
+        self.render_warnings = rerendered.render_warnings;
+        self.sections = rerendered.sections.into_iter().collect();
+        self.section_names = rerendered.section_names;
+        self.toc = rerendered.toc;
+    }
+}
+
+/// A paper's body rendered again, with what depends on it
+struct Rerendered {
+    rendered: Rendered,
+    sections: HashMap<String, String>,
+    section_names: Vec<String>,
+    toc: Vec<String>,
+    is_stub: bool,
+    render_warnings: Vec<String>,
+}
+
+/// A markdown file handed to the processor as a `{filename, content}` object,
+/// optionally with `mtime` in milliseconds since the Unix epoch
+#[derive(Debug, Deserialize)]
+pub struct SourceFile {
+    pub filename: String,
+    pub content: String,
+    #[serde(default)]
+    pub mtime: Option<f64>,
+}
+
+/// Time spent processing one file
+#[derive(Debug, Serialize, Clone)]
+pub struct Timing {
+    pub filename: String,
+    pub micros: u64,
+}
+
+/// A `##` section of a paper's markdown. `start` and `end` are byte offsets
+/// into the paper's `content`, from the heading to the next section's heading.
+#[derive(Debug, Serialize, Clone)]
+pub struct Section {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// A series of papers in reading order
+#[derive(Debug, Serialize, Clone)]
+pub struct Series {
+    pub name: String,
+    pub papers: Vec<SeriesEntry>,
+}
+
+/// A paper's place in a series
+#[derive(Debug, Serialize, Clone)]
+pub struct SeriesEntry {
+    pub slug: String,
+    pub title: String,
+    pub order: Option<i64>,
+}
+
+/// The papers before and after one in its series
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SeriesNeighbors {
+    pub previous: Option<String>,
+    pub next: Option<String>,
+}
+
+/// Where a paper's `lastUpdated` was read from
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct DateSource {
+    pub key: String,
+    pub format: DateFormat,
+}
+
+/// How the loaded papers differ from an earlier set, by slug
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SlugChanges {
+    /// Slugs only in the loaded papers
+    pub added: Vec<String>,
+    /// Slugs only in the earlier papers
+    pub removed: Vec<String>,
+    /// Slugs in both whose `contentHash` differs
+    pub modified: Vec<String>,
+}
+
+/// Totals across the listed papers
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct CorpusStats {
+    pub papers: usize,
+    pub words: usize,
+    #[serde(rename = "readingMinutes")]
+    pub reading_minutes: usize,
+    pub tags: usize,
+    pub authors: usize,
+    pub drafts: usize,
+    pub published: usize,
+    /// The earliest and latest `lastUpdated` values that parse as dates,
+    /// as written
+    #[serde(rename = "firstUpdated")]
+    pub first_updated: Option<String>,
+    #[serde(rename = "lastUpdated")]
+    pub last_updated: Option<String>,
+}
+
+/// How serious a `LintIssue` is
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One problem found by `lint`
+#[derive(Debug, Serialize, Clone)]
+pub struct LintIssue {
+    pub severity: Severity,
+    /// Slug of the paper the problem is in
+    pub slug: String,
+    pub message: String,
 }
 
+/// Every problem in the loaded papers, for failing CI builds
+#[derive(Debug, Serialize, Clone)]
+pub struct LintReport {
+    /// Whether there are no errors; warnings alone still pass
+    pub ok: bool,
+    pub error_count: usize,
+    pub warning_count: usize,
+    pub issues: Vec<LintIssue>,
+}
+
+/// How `combined_html` orders papers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaperOrder {
+    /// Alphabetically by title, ignoring case
+    Title,
+    /// Most recently updated first, undated papers last
+    Date,
+    /// Alphabetically by slug
+    Slug,
+}
+
+impl PaperOrder {
+    /// Parse `title`, `date` or `slug`
+    pub fn parse(order: &str) -> Option<Self> {
+        match order.trim().to_lowercase().as_str() {
+            "title" => Some(PaperOrder::Title),
+            "date" => Some(PaperOrder::Date),
+            "slug" => Some(PaperOrder::Slug),
+            _ => None,
+        }
+    }
+}
+
+/// A tag with the URL-safe slug used to route to it
+#[derive(Debug, Serialize, Clone)]
+pub struct Category {
+    pub display: String,
+    pub slug: String,
+}
+
+/// A tag with the HSL hue used to color it
+#[derive(Debug, Serialize, Clone)]
+pub struct TagColor {
+    pub tag: String,
+    pub hue: u32,
+}
+
+/// Version of the JSON the processor produces, from `get_schema_version`.
+///
+/// It goes up by one whenever a field of an existing output is removed,
+/// renamed or changes type or meaning. Adding fields or new outputs does not
+/// change it, so consumers should ignore fields they do not know.
+pub const SCHEMA_VERSION: u32 = 1;
+
 #[derive(Debug, Serialize)]
 pub struct ProcessedContent {
     pub papers: Vec<Paper>,
@@ -64,6 +463,17 @@ pub struct ProcessedContent {
 #[wasm_bindgen]
 pub struct PaperProcessor {
     papers: Vec<Paper>,
+    config: ProcessorConfig,
+    search_index: Option<SearchIndex>,
+    timings: Vec<Timing>,
+    code_renderer: Option<Box<dyn CodeBlockRenderer>>,
+    heading_id_fn: Option<Box<render::HeadingIdFn>>,
+}
+
+impl Default for PaperProcessor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[wasm_bindgen]
@@ -71,17 +481,104 @@ impl PaperProcessor {
     #[wasm_bindgen(constructor)]
     pub fn new() -> PaperProcessor {
         console_error_panic_hook::set_once();
-        PaperProcessor { papers: Vec::new() }
+        PaperProcessor {
+            papers: Vec::new(),
+            config: ProcessorConfig::default(),
+            search_index: None,
+            timings: Vec::new(),
+            code_renderer: None,
+            heading_id_fn: None,
+        }
     }
 
     /// Process a single markdown file and add it to the collection
     #[wasm_bindgen]
     pub fn process_paper(&mut self, filename: &str, content: &str) -> Result<(), JsValue> {
-        match self.process_single_paper(filename, content) {
-            Ok(paper) => {
-                self.papers.push(paper);
-                Ok(())
+        match self.process(filename, content) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                console_log!("Error processing {}: {}", filename, e);
+                Err(JsValue::from_str(&format!(
+                    "Failed to process paper: {}",
+                    e
+                )))
             }
+        }
+    }
+
+    /// Load papers from a previous `get_papers_json`, then process only those
+    /// of `files` (`{filename, content, mtime?}` objects) that are new or whose
+    /// content changed. Returns the slugs of the papers processed.
+    #[wasm_bindgen]
+    pub fn process_incremental(
+        &mut self,
+        previous_json: &str,
+        files: js_sys::Array,
+    ) -> Result<js_sys::Array, JsValue> {
+        let previous: Vec<Paper> = serde_json::from_str(previous_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid papers JSON: {}", e)))?;
+        let files = files
+            .iter()
+            .map(serde_wasm_bindgen::from_value)
+            .collect::<Result<Vec<SourceFile>, _>>()
+            .map_err(|e| JsValue::from_str(&format!("Invalid source file: {}", e)))?;
+
+        let changed = self
+            .process_changed(previous, &files)
+            .map_err(|e| JsValue::from_str(&format!("Failed to process paper: {}", e)))?;
+        let slugs = js_sys::Array::new();
+        for slug in changed {
+            slugs.push(&JsValue::from_str(&slug));
+        }
+        Ok(slugs)
+    }
+
+    /// Compare the loaded papers with those of a previous `get_papers_json`
+    /// and get the slugs that differ as JSON `{added, removed, modified}`
+    #[wasm_bindgen]
+    pub fn changed_slugs(&self, previous_json: &str) -> Result<String, JsValue> {
+        let previous: Vec<Paper> = serde_json::from_str(previous_json)
+            .map_err(|e| JsValue::from_str(&format!("Invalid papers JSON: {}", e)))?;
+
+        self.to_json(&self.slug_changes(&previous))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize slug changes: {}", e)))
+    }
+
+    /// Extract files from a tar archive whose names end with one of
+    /// `extensions`, like `process_tar_archive_with_extensions`, but fail
+    /// without reading it when it exceeds the configured `max_archive_bytes`
+    #[wasm_bindgen]
+    pub fn extract_tar_archive(
+        &self,
+        tar_data: &[u8],
+        extensions: Vec<String>,
+    ) -> Result<js_sys::Array, JsValue> {
+        let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+        let files = self
+            .tar_archive_files(tar_data, &extensions)
+            .map_err(|e| JsValue::from_str(&format!("Failed to read archive: {}", e)))?;
+        source_files_array(files)
+    }
+
+    /// Process a `{filename, content}` object and add it to the collection
+    #[wasm_bindgen]
+    pub fn process_paper_value(&mut self, file: JsValue) -> Result<(), JsValue> {
+        let file: SourceFile = serde_wasm_bindgen::from_value(file)
+            .map_err(|e| JsValue::from_str(&format!("Invalid source file: {}", e)))?;
+        self.process_paper_with_mtime(&file.filename, &file.content, file.mtime)
+    }
+
+    /// Process a single markdown file modified at `mtime` milliseconds since
+    /// the Unix epoch, used for `lastUpdated` in `mtime` fallback mode
+    #[wasm_bindgen]
+    pub fn process_paper_with_mtime(
+        &mut self,
+        filename: &str,
+        content: &str,
+        mtime: Option<f64>,
+    ) -> Result<(), JsValue> {
+        match self.process_with_mtime(filename, content, mtime.map(|mtime| mtime as i64)) {
+            Ok(_) => Ok(()),
             Err(e) => {
                 console_log!("Error processing {}: {}", filename, e);
                 Err(JsValue::from_str(&format!(
@@ -95,307 +592,4564 @@ impl PaperProcessor {
     /// Get all processed papers as JSON
     #[wasm_bindgen]
     pub fn get_papers_json(&self) -> Result<String, JsValue> {
-        serde_json::to_string_pretty(&self.papers)
+        self.to_json(&self.papers)
             .map_err(|e| JsValue::from_str(&format!("Failed to serialize papers: {}", e)))
     }
 
+    /// Get the version of the JSON formats this processor produces; see
+    /// `SCHEMA_VERSION`
+    #[wasm_bindgen]
+    pub fn get_schema_version(&self) -> u32 {
+        SCHEMA_VERSION
+    }
+
+    /// Get all processed papers as JS objects, without a JSON round trip
+    #[wasm_bindgen]
+    pub fn get_papers(&self) -> Result<JsValue, JsValue> {
+        self.papers
+            .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+            .map_err(|e| JsValue::from_str(&format!("Failed to convert papers: {}", e)))
+    }
+
+    /// Get all processed papers as gzip-compressed JSON (level 9)
+    #[wasm_bindgen]
+    pub fn get_papers_json_gz(&self) -> Result<Vec<u8>, JsValue> {
+        let papers_json = self.get_papers_json()?;
+        json::gzip(papers_json.as_bytes())
+            .map_err(|e| JsValue::from_str(&format!("Failed to compress papers: {}", e)))
+    }
+
+    /// Stream all processed papers as JSON, calling `callback` with each chunk
+    ///
+    /// Chunks concatenate to the same document as `get_papers_json`, but only
+    /// one paper is serialized in memory at a time.
+    #[wasm_bindgen]
+    pub fn write_papers_json_chunked(&self, callback: &js_sys::Function) -> Result<(), JsValue> {
+        json::write_array_chunked(
+            &self.papers,
+            self.config.pretty,
+            |chunk| {
+                callback
+                    .call1(&JsValue::NULL, &JsValue::from_str(&chunk))
+                    .map(|_| ())
+            },
+            |e| JsValue::from_str(&format!("Failed to serialize papers: {}", e)),
+        )
+    }
+
     /// Get paper list (lighter version) as JSON
     #[wasm_bindgen]
     pub fn get_papers_list_json(&self) -> Result<String, JsValue> {
+        let papers_list: Vec<_> = self.listed_papers().map(list_item).collect();
+
+        self.to_json(&papers_list)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize papers list: {}", e)))
+    }
+
+    /// Get the `limit` most recently updated non-draft papers as light list
+    /// JSON, newest first. Papers with unparseable dates come last.
+    #[wasm_bindgen]
+    pub fn get_recent_papers(&self, limit: usize) -> Result<String, JsValue> {
         let papers_list: Vec<_> = self
-            .papers
-            .iter()
-            .map(|paper| {
-                serde_json::json!({
-                    "title": paper.title,
-                    "slug": paper.slug,
-                    "status": paper.status,
-                    "tags": paper.tags.as_ref().unwrap_or(&vec![]),
-                    "summary": paper.summary,
-                    "lastUpdated": paper.last_updated,
-                    "authors": paper.authors
-                })
-            })
+            .recent_papers(limit)
+            .into_iter()
+            .map(list_item)
+            .collect();
+        self.to_json(&papers_list)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize papers list: {}", e)))
+    }
+
+    /// Get the papers whose `lastUpdated` falls between `start` and `end`
+    /// inclusive as light list JSON. A bound without a time of day covers
+    /// its whole day, so `2024-03-01` to `2024-03-31` is all of March.
+    /// Papers with unparseable dates are left out.
+    #[wasm_bindgen]
+    pub fn get_papers_in_range(&self, start: &str, end: &str) -> Result<String, JsValue> {
+        let bound = |date: &str| {
+            dates::parse_date(date)
+                .map(|parsed| parsed.unix_seconds())
+                .ok_or_else(|| JsValue::from_str(&format!("Invalid date: {}", date)))
+        };
+        let start_secs = bound(start)?;
+        let mut end_secs = bound(end)?;
+        if !end.trim().contains(['T', 't', ' ']) {
+            end_secs += 86_399;
+        }
+        if start_secs > end_secs {
+            return Err(JsValue::from_str(&format!(
+                "Range start {} is after its end {}",
+                start, end
+            )));
+        }
+
+        let papers_list: Vec<_> = self
+            .papers_updated_between(start_secs, end_secs)
+            .into_iter()
+            .map(list_item)
             .collect();
+        self.to_json(&papers_list)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize papers list: {}", e)))
+    }
 
-        serde_json::to_string_pretty(&papers_list)
+    /// Get the papers tagged `tag` as a page of light list items, JSON
+    /// `{items, page, total, total_pages}`. Pages start at 1; without
+    /// `per_page` all papers are on page 1.
+    #[wasm_bindgen]
+    pub fn get_papers_by_tag(
+        &self,
+        tag: &str,
+        page: Option<usize>,
+        per_page: Option<usize>,
+    ) -> Result<String, JsValue> {
+        let items: Vec<_> = self.papers_by_tag(tag).into_iter().map(list_item).collect();
+        self.to_json(&paginate(items, page, per_page))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize papers list: {}", e)))
+    }
+
+    /// Get the papers by the author named `author` as a page of light list
+    /// items, like `get_papers_by_tag`
+    #[wasm_bindgen]
+    pub fn get_papers_by_author(
+        &self,
+        author: &str,
+        page: Option<usize>,
+        per_page: Option<usize>,
+    ) -> Result<String, JsValue> {
+        let items: Vec<_> = self
+            .papers_by_author(author)
+            .into_iter()
+            .map(list_item)
+            .collect();
+        self.to_json(&paginate(items, page, per_page))
             .map_err(|e| JsValue::from_str(&format!("Failed to serialize papers list: {}", e)))
     }
 
     /// Get categories as JSON
     #[wasm_bindgen]
     pub fn get_categories_json(&self) -> Result<String, JsValue> {
-        let categories = self.extract_categories();
-        serde_json::to_string_pretty(&categories)
+        let categories = self.categories();
+        self.to_json(&categories)
             .map_err(|e| JsValue::from_str(&format!("Failed to serialize categories: {}", e)))
     }
 
-    /// Get a specific paper by slug as JSON
+    /// Get a `{tag, hue}` entry for every category as JSON. See `tag_hue` for
+    /// how hues are derived.
     #[wasm_bindgen]
-    pub fn get_paper_by_slug(&self, slug: &str) -> Result<String, JsValue> {
-        let paper = self
-            .papers
-            .iter()
-            .find(|p| p.slug == slug)
-            .ok_or_else(|| JsValue::from_str(&format!("Paper not found: {}", slug)))?;
-
-        serde_json::to_string_pretty(paper)
-            .map_err(|e| JsValue::from_str(&format!("Failed to serialize paper: {}", e)))
+    pub fn get_tag_colors_json(&self) -> Result<String, JsValue> {
+        self.to_json(&self.tag_colors())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize tag colors: {}", e)))
     }
 
-    /// Clear all processed papers
+    /// Get the author index as JSON, sorted by surname
     #[wasm_bindgen]
-    pub fn clear(&mut self) {
-        self.papers.clear();
+    pub fn get_authors_json(&self) -> Result<String, JsValue> {
+        self.to_json(&self.authors())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize authors: {}", e)))
     }
 
-    /// Get count of processed papers
+    /// Get a version token for the whole collection, for use as an ETag. It
+    /// depends only on each paper's `contentHash`, not on processing order.
     #[wasm_bindgen]
-    pub fn get_paper_count(&self) -> usize {
-        self.papers.len()
+    pub fn get_collection_etag(&self) -> String {
+        self.collection_etag()
     }
 
-    /// Get all paper slugs
+    /// Get every series with its papers in reading order as JSON
     #[wasm_bindgen]
-    pub fn get_paper_slugs(&self) -> js_sys::Array {
-        let slugs = js_sys::Array::new();
-        for paper in &self.papers {
-            slugs.push(&JsValue::from_str(&paper.slug));
-        }
-        slugs
+    pub fn get_series_json(&self) -> Result<String, JsValue> {
+        self.to_json(&self.series())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize series: {}", e)))
     }
-}
-
-impl PaperProcessor {
-    fn process_single_paper(
-        &self,
-        filename: &str,
-        content: &str,
-    ) -> Result<Paper, Box<dyn std::error::Error>> {
-        let (metadata, markdown) = self.parse_frontmatter(content)?;
-        let sections = self.parse_markdown_sections(&markdown);
-        let toc = self.extract_toc(&markdown);
-
-        let slug = filename.strip_suffix(".md").unwrap_or(filename).to_string();
-
-        // Warn if title is missing
-        if metadata.title.is_none() {
-            console_log!(
-                "WARNING: Paper {} is missing a title in frontmatter!",
-                filename
-            );
-        }
 
-        let paper = Paper {
-            title: metadata.title.clone().unwrap_or_else(|| slug.clone()),
-            slug,
-            filename: filename.to_string(),
-            summary: sections
-                .get("summary")
-                .cloned()
-                .unwrap_or_else(|| "No summary available".to_string()),
-            abstract_text: sections.get("abstract").cloned().unwrap_or_default(),
-            toc: if !toc.is_empty() {
-                toc
-            } else {
-                metadata.toc.unwrap_or_default()
-            },
-            content: markdown.clone(),
-            html: self.markdown_to_html(&markdown),
-            last_updated: metadata.last_updated.unwrap_or_else(|| {
-                // Use current timestamp in ISO format
-                js_sys::Date::new_0().to_iso_string().into()
-            }),
-            authors: metadata.authors.unwrap_or_default(),
-            tags: metadata.tags,
-            status: metadata.status,
-            extra: metadata.extra,
-        };
+    /// Get the previous and next slugs in a paper's series as JSON
+    /// `{previous, next}`; both are null for papers outside a series
+    #[wasm_bindgen]
+    pub fn get_series_neighbors(&self, slug: &str) -> Result<String, JsValue> {
+        let neighbors = self
+            .series_neighbors(slug)
+            .ok_or_else(|| JsValue::from_str(&format!("Paper not found: {}", slug)))?;
 
-        Ok(paper)
+        self.to_json(&neighbors)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize series: {}", e)))
     }
 
-    fn parse_frontmatter(
-        &self,
-        content: &str,
-    ) -> Result<(PaperMetadata, String), Box<dyn std::error::Error>> {
-        let frontmatter_regex = Regex::new(r"^---\s*\n([\s\S]*?)\n---\s*\n([\s\S]*)$")?;
+    /// Get categories as JSON `[{display, slug}]`, with a URL-safe slug for
+    /// routing alongside each tag as written
+    #[wasm_bindgen]
+    pub fn get_category_slugs_json(&self) -> Result<String, JsValue> {
+        self.to_json(&self.category_slugs())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize categories: {}", e)))
+    }
 
-        if let Some(captures) = frontmatter_regex.captures(content) {
-            let yaml_content = &captures[1];
-            let markdown_content = &captures[2];
+    /// Get the distinct statuses used across all papers as JSON
+    #[wasm_bindgen]
+    pub fn get_statuses_json(&self) -> Result<String, JsValue> {
+        self.to_json(&self.statuses())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize statuses: {}", e)))
+    }
 
-            let metadata: PaperMetadata = serde_yaml::from_str(yaml_content)?;
-            Ok((metadata, markdown_content.to_string()))
-        } else {
-            console_log!(
-                "No frontmatter found in content. This will cause issues in the web interface."
-            );
-            Ok((
-                PaperMetadata {
-                    title: None,
-                    authors: None,
-                    tags: None,
-                    status: None,
-                    last_updated: None,
-                    toc: None,
-                    extra: HashMap::new(),
-                },
-                content.to_string(),
-            ))
-        }
-    }
-
-    fn parse_markdown_sections(&self, markdown: &str) -> HashMap<String, String> {
-        let mut sections = HashMap::new();
-        let lines: Vec<&str> = markdown.lines().collect();
-        let mut current_section: Option<String> = None;
-        let mut current_content = Vec::new();
+    /// Get totals across the listed papers as JSON `{papers, words,
+    /// readingMinutes, tags, authors, drafts, published, firstUpdated,
+    /// lastUpdated}`
+    #[wasm_bindgen]
+    pub fn get_corpus_stats_json(&self) -> Result<String, JsValue> {
+        self.to_json(&self.corpus_stats())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize corpus stats: {}", e)))
+    }
 
-        for line in lines {
-            if line.starts_with("## ") {
-                // Save previous section if exists
-                if let Some(section) = &current_section {
-                    sections.insert(
-                        section.clone(),
-                        current_content.join("\n").trim().to_string(),
-                    );
-                }
-                // Start new section
-                current_section = Some(line[3..].to_lowercase());
-                current_content.clear();
-            } else if current_section.is_some() {
-                current_content.push(line);
-            }
-        }
+    /// Generate an RSS 2.0 feed of all non-draft papers, newest first. Items
+    /// link to `{base_url}/papers/{slug}`.
+    #[wasm_bindgen]
+    pub fn generate_rss(&self, base_url: &str, channel_title: &str) -> String {
+        feed::rss(
+            &feed::entries(self.listed_papers()),
+            base_url,
+            channel_title,
+        )
+    }
 
-        // Save final section
-        if let Some(section) = current_section {
-            sections.insert(section, current_content.join("\n").trim().to_string());
-        }
+    /// Generate an Atom 1.0 feed of all non-draft papers, newest first.
+    /// Entries are identified by and link to `{base_url}/papers/{slug}`.
+    #[wasm_bindgen]
+    pub fn generate_atom(&self, base_url: &str, feed_title: &str) -> String {
+        feed::atom(&feed::entries(self.listed_papers()), base_url, feed_title)
+    }
 
-        sections
+    /// Generate an RSS 2.0 feed of the non-draft papers tagged `tag`. An
+    /// unknown tag gives a feed with no items.
+    #[wasm_bindgen]
+    pub fn generate_rss_for_tag(&self, tag: &str, base_url: &str, channel_title: &str) -> String {
+        let papers = self.papers_by_tag(tag);
+        feed::rss(&feed::entries(papers), base_url, channel_title)
     }
 
-    fn extract_toc(&self, markdown: &str) -> Vec<String> {
-        // Find the TOC section manually since Rust regex doesn't support lookahead
-        if let Some(start) = markdown.find("## Table of Contents") {
-            let after_toc = &markdown[start..];
+    /// Get a specific paper by slug as JSON
+    #[wasm_bindgen]
+    pub fn get_paper_by_slug(&self, slug: &str) -> Result<String, JsValue> {
+        let paper = self
+            .paper(slug)
+            .ok_or_else(|| JsValue::from_str(&format!("Paper not found: {}", slug)))?;
 
-            // Find the end by looking for the next ## header or end of string
-            let end = after_toc.find("\n## ").unwrap_or(after_toc.len());
-            let toc_section = &after_toc[..end];
+        self.to_json(paper)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize paper: {}", e)))
+    }
 
-            let item_regex = Regex::new(r"^\d+\.\s+\*\*(.*?)\*\*").unwrap();
+    /// Get a paper's sections as a JSON `{section_name: raw_markdown}` object
+    #[wasm_bindgen]
+    pub fn get_sections_json(&self, slug: &str) -> Result<String, JsValue> {
+        let paper = self
+            .paper(slug)
+            .ok_or_else(|| JsValue::from_str(&format!("Paper not found: {}", slug)))?;
 
-            toc_section
-                .lines()
-                .filter_map(|line| item_regex.captures(line).map(|cap| cap[1].to_string()))
-                .collect()
-        } else {
-            Vec::new()
-        }
+        self.to_json(&paper.sections)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize sections: {}", e)))
     }
 
-    fn markdown_to_html(&self, markdown: &str) -> String {
-        let mut options = Options::empty();
-        options.insert(Options::ENABLE_STRIKETHROUGH);
-        options.insert(Options::ENABLE_TABLES);
-        options.insert(Options::ENABLE_FOOTNOTES);
-        options.insert(Options::ENABLE_TASKLISTS);
+    /// Get schema.org `ScholarlyArticle` JSON-LD for a paper, for a
+    /// `<script type="application/ld+json">` tag
+    #[wasm_bindgen]
+    pub fn get_json_ld(&self, slug: &str, base_url: &str) -> Result<String, JsValue> {
+        let paper = self
+            .paper(slug)
+            .ok_or_else(|| JsValue::from_str(&format!("Paper not found: {}", slug)))?;
 
-        let parser = Parser::new_ext(markdown, options);
-        let mut html_output = String::new();
-        html::push_html(&mut html_output, parser);
+        self.to_json(&meta::json_ld(paper, base_url))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize JSON-LD: {}", e)))
+    }
 
-        // Add id attributes to headings to match Node.js marked behavior
-        let heading_regex = Regex::new(r"<h(\d)>([^<]+)</h\d>").unwrap();
-        heading_regex
-            .replace_all(&html_output, |caps: &regex::Captures| {
-                let level = &caps[1];
-                let text = &caps[2];
-                let id = text.to_lowercase().replace(" ", "-").replace(
-                    &[
-                        '!', '?', ':', ';', ',', '.', '"', '\'', '(', ')', '[', ']', '{', '}',
-                    ],
-                    "",
-                );
-                format!("<h{} id=\"{}\">{}</h{}>", level, id, text, level)
+    /// Get the `top_n` most frequent terms in a paper's prose, leaving out
+    /// code and stopwords, as JSON `[{term, count}]`
+    #[wasm_bindgen]
+    pub fn get_word_frequencies(&self, slug: &str, top_n: usize) -> Result<String, JsValue> {
+        let terms = self
+            .word_frequencies(slug, top_n)
+            .ok_or_else(|| JsValue::from_str(&format!("Paper not found: {}", slug)))?;
+
+        self.to_json(&terms)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize word frequencies: {}", e)))
+    }
+
+    /// Get up to `limit` tags the paper does not have yet, best first, as a
+    /// JSON array. Existing tags are ranked by how often their words occur in
+    /// the paper and how often they appear alongside its tags on other papers.
+    /// When no other paper is tagged, the paper's most frequent terms are
+    /// suggested instead.
+    #[wasm_bindgen]
+    pub fn suggest_tags(&self, slug: &str, limit: usize) -> Result<String, JsValue> {
+        let tags = self
+            .tag_suggestions(slug, limit)
+            .ok_or_else(|| JsValue::from_str(&format!("Paper not found: {}", slug)))?;
+
+        self.to_json(&tags)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize tags: {}", e)))
+    }
+
+    /// Get a short HTML blurb for a paper card: its summary, then the first
+    /// sentence of its abstract when it has one, both as escaped plain text
+    /// in a `<div class="paper-card">`
+    #[wasm_bindgen]
+    pub fn get_card_html(&self, slug: &str) -> Result<String, JsValue> {
+        self.card_html(slug)
+            .ok_or_else(|| JsValue::from_str(&format!("Paper not found: {}", slug)))
+    }
+
+    /// Get a paper's sections in document order with their byte offsets into
+    /// its `content`, as JSON `[{name, start, end, text}]`
+    #[wasm_bindgen]
+    pub fn get_section_offsets_json(&self, slug: &str) -> Result<String, JsValue> {
+        let sections = self
+            .section_offsets(slug)
+            .ok_or_else(|| JsValue::from_str(&format!("Paper not found: {}", slug)))?;
+
+        self.to_json(&sections)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize sections: {}", e)))
+    }
+
+    /// Render one section of a paper: the heading whose id is `heading_id`
+    /// and everything up to the next heading of the same or a higher level
+    #[wasm_bindgen]
+    pub fn get_section_html_by_id(&self, slug: &str, heading_id: &str) -> Result<String, JsValue> {
+        if self.paper(slug).is_none() {
+            return Err(JsValue::from_str(&format!("Paper not found: {}", slug)));
+        }
+        self.section_html_by_id(slug, heading_id)
+            .ok_or_else(|| JsValue::from_str(&format!("Heading not found: {}", heading_id)))
+    }
+
+    /// Get every `[[slug]]`, `[[slug#anchor]]` and `#anchor` link that points
+    /// at no paper or heading, as JSON `[{slug, link}]`
+    #[wasm_bindgen]
+    pub fn find_broken_links(&self) -> Result<String, JsValue> {
+        self.to_json(&self.broken_links())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize broken links: {}", e)))
+    }
+
+    /// Fail with a message listing every broken link, for use in CI. This is
+    /// the strict counterpart of `find_broken_links`.
+    #[wasm_bindgen(js_name = assert_no_broken_links)]
+    pub fn js_assert_no_broken_links(&self) -> Result<(), JsValue> {
+        self.assert_no_broken_links().map_err(to_js_error)
+    }
+
+    /// Get the `<head>` tags for a paper's page: description, Open Graph
+    /// tags and a canonical link, which defaults to `{base_url}/papers/{slug}`
+    #[wasm_bindgen]
+    pub fn get_meta_tags(&self, slug: &str, base_url: &str) -> Result<String, JsValue> {
+        let paper = self
+            .paper(slug)
+            .ok_or_else(|| JsValue::from_str(&format!("Paper not found: {}", slug)))?;
+
+        Ok(meta::meta_tags(paper, base_url))
+    }
+
+    /// Get the warnings recorded for each paper that has any, as JSON
+    #[wasm_bindgen]
+    pub fn get_warnings_json(&self) -> Result<String, JsValue> {
+        let warnings: Vec<_> = self
+            .papers
+            .iter()
+            .filter(|paper| !paper.warnings.is_empty())
+            .map(|paper| {
+                serde_json::json!({
+                    "slug": paper.slug,
+                    "filename": paper.filename,
+                    "warnings": paper.warnings
+                })
             })
-            .to_string()
+            .collect();
+
+        self.to_json(&warnings)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize warnings: {}", e)))
     }
 
-    fn extract_categories(&self) -> Vec<String> {
-        let mut categories = std::collections::HashSet::new();
+    /// Replace `{{include: slug#section}}` directives with the raw markdown
+    /// of that paper's section and re-render the papers that had any, as
+    /// `rerender_all` does. Call this once all papers are loaded. Missing
+    /// sections and include cycles are left as written and reported in the
+    /// paper's warnings.
+    #[wasm_bindgen]
+    pub fn resolve_includes(&mut self) {
+        let lookup = |slug: &str, section: &str| {
+            self.paper(slug)
+                .and_then(|paper| paper.sections.get(section))
+                .map(String::as_str)
+        };
 
-        for paper in &self.papers {
-            if let Some(ref tags) = paper.tags {
-                for tag in tags {
-                    categories.insert(tag.clone());
-                }
+        let mut resolved = Vec::new();
+        for (idx, paper) in self.papers.iter().enumerate() {
+            if paper.redirect_url.is_some() {
+                continue;
+            }
+            let mut problems = Vec::new();
+            let content = includes::expand(&paper.content, &lookup, &mut problems);
+            if content != paper.content || !problems.is_empty() {
+                let rerendered = self.rerender(paper, &content);
+                resolved.push((idx, content, rerendered, problems));
             }
         }
 
-        let mut sorted_categories: Vec<String> = categories.into_iter().collect();
-        sorted_categories.sort();
-        sorted_categories
+        for (idx, content, rerendered, problems) in resolved {
+            let paper = &mut self.papers[idx];
+            paper.content = content;
+            for problem in problems {
+                warn(&mut paper.source_warnings, problem);
+            }
+            paper.set_rerendered(rerendered);
+        }
+        self.search_index = None;
     }
-}
 
-/// Utility function to extract files from a tar archive
-#[wasm_bindgen]
-pub fn process_tar_archive(tar_data: &[u8]) -> Result<js_sys::Array, JsValue> {
-    let files = js_sys::Array::new();
+    /// Re-render every loaded paper with the current configuration,
+    /// updating `html`, `headings`, `figures`, the body flags, sections,
+    /// `toc` and `renderWarnings`. Warnings and errors from frontmatter and
+    /// includes are kept as they were.
+    #[wasm_bindgen]
+    pub fn rerender_all(&mut self) {
+        let rerendered: Vec<_> = self
+            .papers
+            .iter()
+            .map(|paper| self.rerender(paper, &paper.content))
+            .collect();
 
-    // Simple tar parsing - this is a basic implementation
-    // For production, you might want to use a proper tar library
-    let mut offset = 0;
+        for (paper, rerendered) in self.papers.iter_mut().zip(rerendered) {
+            paper.set_rerendered(rerendered);
+        }
+        self.search_index = None;
+    }
 
-    while offset + 512 <= tar_data.len() {
-        let header = &tar_data[offset..offset + 512];
+    /// Clear all processed papers
+    #[wasm_bindgen]
+    pub fn clear(&mut self) {
+        self.papers.clear();
+        self.search_index = None;
+        self.timings.clear();
+    }
 
-        // Check if this is the end of the archive (all zeros)
-        if header.iter().all(|&b| b == 0) {
-            break;
+    /// Get count of processed papers
+    #[wasm_bindgen]
+    pub fn get_paper_count(&self) -> usize {
+        self.papers.len()
+    }
+
+    /// Get all paper slugs
+    #[wasm_bindgen]
+    pub fn get_paper_slugs(&self) -> js_sys::Array {
+        let slugs = js_sys::Array::new();
+        for paper in &self.papers {
+            slugs.push(&JsValue::from_str(&paper.slug));
         }
+        slugs
+    }
 
-        // Parse filename (first 100 bytes, null-terminated)
-        let filename_bytes = &header[0..100];
-        let filename_end = filename_bytes.iter().position(|&b| b == 0).unwrap_or(100);
-        let filename = String::from_utf8_lossy(&filename_bytes[..filename_end]).to_string();
+    /// Treat missing titles, missing frontmatter, bad dates and malformed
+    /// authors as errors instead of warnings
+    #[wasm_bindgen]
+    pub fn set_strict(&mut self, strict: bool) {
+        self.config.strict = strict;
+    }
+
+    /// Reject files larger than `bytes`; pass `undefined` to remove the limit
+    #[wasm_bindgen]
+    pub fn set_max_content_bytes(&mut self, bytes: Option<usize>) {
+        self.config.max_content_bytes = bytes;
+    }
+
+    /// Refuse to process more than `count` papers; pass `undefined` to
+    /// remove the limit
+    #[wasm_bindgen]
+    pub fn set_max_papers(&mut self, count: Option<usize>) {
+        self.config.max_papers = count;
+    }
+
+    /// Make `extract_tar_archive` reject archives larger than `bytes`; pass
+    /// `undefined` to remove the limit
+    #[wasm_bindgen]
+    pub fn set_max_archive_bytes(&mut self, bytes: Option<usize>) {
+        self.config.max_archive_bytes = bytes;
+    }
+
+    /// Enable or disable converting `:shortcode:` emoji when rendering
+    #[wasm_bindgen]
+    pub fn set_emoji_shortcodes(&mut self, enabled: bool) {
+        self.config.emoji_shortcodes = enabled;
+    }
+
+    /// Enable or disable linking bare URLs when rendering
+    #[wasm_bindgen]
+    pub fn set_autolink(&mut self, enabled: bool) {
+        self.config.autolink = enabled;
+    }
+
+    /// Enable or disable wrapping tables in a `table-wrapper` div
+    #[wasm_bindgen]
+    pub fn set_wrap_tables(&mut self, enabled: bool) {
+        self.config.wrap_tables = enabled;
+    }
+
+    /// Expand tabs in line indentation to stops every `width` columns before
+    /// rendering; `None` (the default) leaves them to pulldown-cmark
+    #[wasm_bindgen]
+    pub fn set_tab_width(&mut self, width: Option<usize>) {
+        self.config.tab_width = width;
+    }
+
+    /// Enable or disable numbering code block lines
+    #[wasm_bindgen]
+    pub fn set_code_line_numbers(&mut self, enabled: bool) {
+        self.config.code_line_numbers = enabled;
+    }
+
+    /// Choose the highlighting theme a custom code block renderer uses, one
+    /// of syntect's default themes such as `InspiredGitHub` or
+    /// `base16-ocean.dark`; `undefined` leaves it to the renderer
+    #[wasm_bindgen(js_name = set_syntax_theme)]
+    pub fn js_set_syntax_theme(&mut self, theme: Option<String>) -> Result<(), JsValue> {
+        self.set_syntax_theme(theme.as_deref()).map_err(to_js_error)
+    }
+
+    /// Enable or disable rendering `mermaid` fences as `<div class="mermaid">`
+    #[wasm_bindgen]
+    pub fn set_mermaid(&mut self, enabled: bool) {
+        self.config.mermaid = enabled;
+    }
 
-        // Parse file size (12 bytes starting at offset 124, octal)
-        let size_bytes = &header[124..136];
-        let size_end = size_bytes
+    /// Choose what `lastUpdated` becomes when frontmatter has none: `now`
+    /// (the default, which changes on every build), `mtime` (the file's
+    /// modification time when passed in), `empty` or `epoch`
+    #[wasm_bindgen(js_name = set_last_updated_fallback)]
+    pub fn js_set_last_updated_fallback(&mut self, mode: &str) -> Result<(), JsValue> {
+        self.set_last_updated_fallback(mode).map_err(to_js_error)
+    }
+
+    /// Set the frontmatter keys `lastUpdated` is read from, such as
+    /// `["lastUpdated", "updated", "date"]`, tried in order. The default is
+    /// `lastUpdated` alone.
+    #[wasm_bindgen]
+    pub fn set_date_keys(&mut self, keys: Vec<String>) {
+        self.config.date_keys = keys;
+    }
+
+    /// Set the date formats tried, in order, on each date key: a
+    /// comma-separated list of `iso`, `dmy` (`31/12/2024`) and `mdy`
+    /// (`12/31/2024`). With both `dmy` and `mdy`, a date only valid in one
+    /// of them is read that way and an ambiguous one such as `03/04/2024` by
+    /// whichever comes first. The default is `iso`.
+    #[wasm_bindgen(js_name = set_date_formats)]
+    pub fn js_set_date_formats(&mut self, formats: &str) -> Result<(), JsValue> {
+        self.set_date_formats(formats).map_err(to_js_error)
+    }
+
+    /// Cut abstracts longer than this many characters at a word boundary,
+    /// keeping the whole text in `abstractFull`; `None` means no limit
+    #[wasm_bindgen]
+    pub fn set_abstract_max_chars(&mut self, max_chars: Option<usize>) {
+        self.config.abstract_max_chars = max_chars;
+    }
+
+    /// Enable or disable using the first `#` heading as the title when
+    /// frontmatter has none, before falling back to the slug
+    #[wasm_bindgen]
+    pub fn set_title_from_heading(&mut self, enabled: bool) {
+        self.config.title_from_heading = enabled;
+    }
+
+    /// Enable or disable removing a leading `#` heading that matches the
+    /// title from the rendered body
+    #[wasm_bindgen]
+    pub fn set_strip_title_heading(&mut self, enabled: bool) {
+        self.config.strip_title_heading = enabled;
+    }
+
+    /// Route external links through a URL template such as
+    /// `https://go.me/?u={url}`, where `{url}` is the encoded original link;
+    /// `None` leaves links as written
+    #[wasm_bindgen]
+    pub fn set_external_link_wrapper(&mut self, template: Option<String>) {
+        self.config.external_link_wrapper = template;
+    }
+
+    /// Link `@username` mentions through a URL template such as
+    /// `https://github.com/{user}`; `None` (the default) leaves them as text
+    #[wasm_bindgen]
+    pub fn set_mention_url(&mut self, template: Option<String>) {
+        self.config.mention_url = template;
+    }
+
+    /// Link `#123` issue references through a URL template such as
+    /// `https://github.com/org/repo/issues/{number}`; `None` (the default)
+    /// leaves them as text
+    #[wasm_bindgen]
+    pub fn set_issue_url(&mut self, template: Option<String>) {
+        self.config.issue_url = template;
+    }
+
+    /// Choose how HTML written in markdown is rendered: `allow` (the
+    /// default) passes it through, `sanitize` strips scripting from it and
+    /// `escape` shows it as text
+    #[wasm_bindgen(js_name = set_raw_html)]
+    pub fn js_set_raw_html(&mut self, mode: &str) -> Result<(), JsValue> {
+        self.set_raw_html(mode).map_err(to_js_error)
+    }
+
+    /// Choose where `toc` comes from: `manual` (a `## Table of Contents`
+    /// section), `frontmatter` or `auto` (the section headings), or a
+    /// comma-separated list tried in order. The default is `manual,frontmatter`.
+    #[wasm_bindgen(js_name = set_toc_source)]
+    pub fn js_set_toc_source(&mut self, source: &str) -> Result<(), JsValue> {
+        self.set_toc_source(source).map_err(to_js_error)
+    }
+
+    /// Make heading ids with `id_fn`, which is called with the heading text
+    /// and returns the id, in place of the built-in ones. Calls that throw or
+    /// return a non-string fall back to the built-in id. `undefined` restores
+    /// the built-in ids.
+    #[wasm_bindgen]
+    pub fn set_heading_id_fn(&mut self, id_fn: Option<js_sys::Function>) {
+        self.heading_id_fn = id_fn.map(|id_fn| {
+            Box::new(move |text: &str| {
+                id_fn
+                    .call1(&JsValue::NULL, &JsValue::from_str(text))
+                    .ok()
+                    .and_then(|id| id.as_string())
+                    .unwrap_or_else(|| render::heading_id(text))
+            }) as Box<render::HeadingIdFn>
+        });
+    }
+
+    /// Choose how heading ids are made: `kebab` (the default) as Node.js
+    /// marked does, `github` as GitHub does with repeated ids numbered, or
+    /// `none` for no ids. A function from `set_heading_id_fn` replaces the
+    /// `kebab` and `github` slugs.
+    #[wasm_bindgen(js_name = set_heading_id_style)]
+    pub fn js_set_heading_id_style(&mut self, style: &str) -> Result<(), JsValue> {
+        self.set_heading_id_style(style).map_err(to_js_error)
+    }
+
+    /// Split sections at headings of this level, 1 to 6; the default is 2
+    #[wasm_bindgen(js_name = set_section_level)]
+    pub fn js_set_section_level(&mut self, level: u32) -> Result<(), JsValue> {
+        self.set_section_level(level).map_err(to_js_error)
+    }
+
+    /// Flag papers with fewer than this many words as stubs; 0 disables it
+    #[wasm_bindgen]
+    pub fn set_stub_word_threshold(&mut self, threshold: usize) {
+        self.config.stub_word_threshold = threshold;
+    }
+
+    /// Set section headings to leave out of the sections map, such as
+    /// `References`. Matching is case-insensitive.
+    #[wasm_bindgen]
+    pub fn set_excluded_sections(&mut self, sections: Vec<String>) {
+        self.config.excluded_sections = sections
             .iter()
-            .position(|&b| b == 0 || b == b' ')
-            .unwrap_or(12);
-        let size_str = String::from_utf8_lossy(&size_bytes[..size_end]);
-        let size = u64::from_str_radix(&size_str, 8).unwrap_or(0);
+            .map(|section| section.trim().to_lowercase())
+            .collect();
+    }
 
-        offset += 512; // Move past header
+    /// Set the headings of the section a manual `toc` is read from, such as
+    /// `Contents` or `Índice`, tried in order. Matching is case-insensitive.
+    /// The default is `Table of Contents`.
+    #[wasm_bindgen]
+    pub fn set_toc_headings(&mut self, headings: Vec<String>) {
+        self.config.toc_headings = headings
+            .iter()
+            .map(|heading| heading.trim().to_lowercase())
+            .collect();
+    }
+
+    /// Prefix heading ids as `{prefix}__{id}`, where `{slug}` expands to each
+    /// paper's slug, and in-page links and footnotes to match; pass
+    /// `undefined` for unprefixed ids
+    #[wasm_bindgen]
+    pub fn set_heading_id_prefix(&mut self, prefix: Option<String>) {
+        self.config.heading_id_prefix = prefix;
+    }
+
+    /// Read frontmatter keys under other names, from an `{alias: key}` object
+    /// such as `{updated: "lastUpdated", keywords: "tags"}`, replacing any set
+    /// before. A paper that has both an alias and its key keeps the key's value.
+    #[wasm_bindgen]
+    pub fn set_field_aliases(&mut self, aliases: JsValue) -> Result<(), JsValue> {
+        self.config.field_aliases = serde_wasm_bindgen::from_value(aliases)
+            .map_err(|e| JsValue::from_str(&format!("Invalid field aliases: {}", e)))?;
+        Ok(())
+    }
+
+    /// Read the frontmatter key `alias` as `key`
+    #[wasm_bindgen]
+    pub fn set_field_alias(&mut self, alias: &str, key: &str) {
+        self.config
+            .field_aliases
+            .insert(alias.to_string(), key.to_string());
+    }
+
+    /// Set the placeholder summaries from a `{lang: text}` object, replacing
+    /// any set before. Papers declare their language with `lang` or `language`
+    /// in frontmatter; unmatched languages fall back to English.
+    #[wasm_bindgen]
+    pub fn set_default_summaries(&mut self, summaries: JsValue) -> Result<(), JsValue> {
+        let summaries: BTreeMap<String, String> = serde_wasm_bindgen::from_value(summaries)
+            .map_err(|e| JsValue::from_str(&format!("Invalid default summaries: {}", e)))?;
+        self.config.default_summaries = summaries
+            .into_iter()
+            .map(|(lang, text)| (lang.to_lowercase(), text))
+            .collect();
+        Ok(())
+    }
+
+    /// Set the placeholder summary for a single language
+    #[wasm_bindgen]
+    pub fn set_default_summary(&mut self, lang: &str, text: &str) {
+        self.config
+            .default_summaries
+            .insert(lang.to_lowercase(), text.to_string());
+    }
+
+    /// Enable or disable recording per-file processing times
+    #[wasm_bindgen]
+    pub fn set_collect_timings(&mut self, enabled: bool) {
+        self.config.collect_timings = enabled;
+    }
+
+    /// Get the events pulldown-cmark parses `markdown` into, as JSON
+    /// `[{kind, tag?, info?, len?}]`, for diagnosing odd rendering. This
+    /// parses again and is not used when processing papers.
+    #[wasm_bindgen]
+    pub fn debug_parse(&self, markdown: &str) -> Result<String, JsValue> {
+        self.to_json(&render::summarize_events(markdown))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize events: {}", e)))
+    }
+
+    /// Check every loaded paper and report all problems as JSON `{ok,
+    /// error_count, warning_count, issues: [{severity, slug, message}]}`.
+    /// Problems strict mode rejects and broken cross-paper links are errors;
+    /// everything else in papers' warnings is a warning.
+    #[wasm_bindgen]
+    pub fn lint_corpus(&self) -> Result<String, JsValue> {
+        self.to_json(&self.lint())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize lint report: {}", e)))
+    }
+
+    /// Render every non-draft paper into one HTML fragment for printing or
+    /// offline reading, ordered by `title`, `date` or `slug`. A contents list
+    /// comes first, then each paper in an `<article id="{slug}">`. Heading
+    /// ids, in-page links and footnotes are prefixed `{slug}__` so papers
+    /// don't collide.
+    #[wasm_bindgen]
+    pub fn render_combined_html(&self, order_by: &str) -> Result<String, JsValue> {
+        let order = PaperOrder::parse(order_by)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown paper order: {}", order_by)))?;
+        Ok(self.combined_html(order))
+    }
+
+    /// Get recorded processing times as JSON, slowest first
+    #[wasm_bindgen]
+    pub fn get_timings_json(&self) -> Result<String, JsValue> {
+        self.to_json(&self.timings())
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize timings: {}", e)))
+    }
+
+    /// Choose between indented (default) and compact JSON output
+    #[wasm_bindgen]
+    pub fn set_pretty(&mut self, pretty: bool) {
+        self.config.pretty = pretty;
+    }
+
+    /// Enable or disable English stopword removal when searching
+    #[wasm_bindgen]
+    pub fn set_search_stopwords(&mut self, enabled: bool) {
+        self.config.tokenizer.stopwords = enabled;
+        self.search_index = None;
+    }
+
+    /// Enable or disable Porter stemming of search terms
+    #[wasm_bindgen]
+    pub fn set_search_stemming(&mut self, enabled: bool) {
+        self.config.tokenizer.stemming = enabled;
+        self.search_index = None;
+    }
+
+    /// Build the search index over all processed papers
+    #[wasm_bindgen]
+    pub fn build_search_index(&mut self) {
+        self.search_index = Some(SearchIndex::build(&self.papers, self.config.tokenizer));
+    }
+
+    /// Search papers by substring match against index terms, as JSON
+    #[wasm_bindgen]
+    pub fn search(&self, query: &str) -> Result<String, JsValue> {
+        self.to_json(&self.search_results(query))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize search results: {}", e)))
+    }
+
+    /// Search papers allowing up to `max_distance` character edits per query term, as JSON
+    #[wasm_bindgen]
+    pub fn search_fuzzy(&self, query: &str, max_distance: usize) -> Result<String, JsValue> {
+        self.to_json(&self.fuzzy_search_results(query, max_distance))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize search results: {}", e)))
+    }
+}
+
+/// Native API, for using the processor from Rust without going through `JsValue`
+impl PaperProcessor {
+    /// A processor with `config` in place of the defaults
+    pub fn with_config(config: ProcessorConfig) -> PaperProcessor {
+        PaperProcessor {
+            config,
+            ..PaperProcessor::new()
+        }
+    }
+
+    /// The processor's configuration
+    pub fn config(&self) -> &ProcessorConfig {
+        &self.config
+    }
+
+    /// The processor's configuration, to change directly. Papers already
+    /// processed keep their output until `rerender_all`.
+    pub fn config_mut(&mut self) -> &mut ProcessorConfig {
+        self.search_index = None;
+        &mut self.config
+    }
+
+    /// `js_set_last_updated_fallback` for Rust callers
+    pub fn set_last_updated_fallback(&mut self, mode: &str) -> Result<(), ProcessError> {
+        self.config.last_updated_fallback = LastUpdatedFallback::parse(mode).ok_or_else(|| {
+            ProcessError::InvalidConfig(format!("Unknown lastUpdated fallback: {}", mode))
+        })?;
+        Ok(())
+    }
+
+    /// `js_set_date_formats` for Rust callers
+    pub fn set_date_formats(&mut self, formats: &str) -> Result<(), ProcessError> {
+        self.config.date_formats = DateFormat::parse_list(formats).ok_or_else(|| {
+            ProcessError::InvalidConfig(format!("Unknown date format: {}", formats))
+        })?;
+        Ok(())
+    }
+
+    /// `js_set_raw_html` for Rust callers
+    pub fn set_raw_html(&mut self, mode: &str) -> Result<(), ProcessError> {
+        self.config.raw_html = RawHtml::parse(mode).ok_or_else(|| {
+            ProcessError::InvalidConfig(format!("Unknown raw HTML mode: {}", mode))
+        })?;
+        Ok(())
+    }
+
+    /// `js_set_toc_source` for Rust callers
+    pub fn set_toc_source(&mut self, source: &str) -> Result<(), ProcessError> {
+        self.config.toc_source = TocSource::parse(source).ok_or_else(|| {
+            ProcessError::InvalidConfig(format!("Unknown TOC source: {}", source))
+        })?;
+        Ok(())
+    }
 
-        if size > 0 && filename.ends_with(".md") {
-            let content_end = offset + size as usize;
-            if content_end <= tar_data.len() {
-                let content = &tar_data[offset..content_end];
-                let content_str = String::from_utf8_lossy(content).to_string();
+    /// `js_set_heading_id_style` for Rust callers
+    pub fn set_heading_id_style(&mut self, style: &str) -> Result<(), ProcessError> {
+        self.config.heading_id_style = HeadingIdStyle::parse(style).ok_or_else(|| {
+            ProcessError::InvalidConfig(format!("Unknown heading id style: {}", style))
+        })?;
+        Ok(())
+    }
 
-                let file_obj = js_sys::Object::new();
-                js_sys::Reflect::set(&file_obj, &"filename".into(), &filename.into())?;
-                js_sys::Reflect::set(&file_obj, &"content".into(), &content_str.into())?;
-                files.push(&file_obj);
+    /// `js_set_syntax_theme` for Rust callers
+    pub fn set_syntax_theme(&mut self, theme: Option<&str>) -> Result<(), ProcessError> {
+        if let Some(theme) = theme {
+            if !SYNTAX_THEMES.contains(&theme) {
+                return Err(ProcessError::InvalidConfig(format!(
+                    "Unknown syntax theme: {} (expected one of {})",
+                    theme,
+                    SYNTAX_THEMES.join(", ")
+                )));
             }
+        }
+        self.config.syntax_theme = theme.map(str::to_string);
+        Ok(())
+    }
 
-            // Round up to next 512-byte boundary
-            let padded_size = ((size + 511) / 512) * 512;
-            offset += padded_size as usize;
-        } else {
-            // Skip non-markdown files or empty files
-            let padded_size = ((size + 511) / 512) * 512;
-            offset += padded_size as usize;
+    /// `js_set_section_level` for Rust callers
+    pub fn set_section_level(&mut self, level: u32) -> Result<(), ProcessError> {
+        if !(1..=6).contains(&level) {
+            return Err(ProcessError::InvalidConfig(format!(
+                "Section heading level must be 1 to 6: {}",
+                level
+            )));
+        }
+        self.config.section_level = level;
+        Ok(())
+    }
+
+    /// Process a single markdown file, add it to the collection and return it
+    pub fn process(&mut self, filename: &str, content: &str) -> Result<&Paper, ProcessError> {
+        self.process_with_mtime(filename, content, None)
+    }
+
+    /// Like `process`, for a file modified at `mtime` milliseconds since the
+    /// Unix epoch
+    pub fn process_with_mtime(
+        &mut self,
+        filename: &str,
+        content: &str,
+        mtime: Option<i64>,
+    ) -> Result<&Paper, ProcessError> {
+        if let Some(limit) = self.config.max_papers {
+            if self.papers.len() >= limit {
+                return Err(ProcessError::CapacityExceeded { limit });
+            }
+        }
+
+        let started = self.config.collect_timings.then(now_micros);
+        let result = self.process_single_paper(filename, content, mtime);
+        if let Some(started) = started {
+            self.timings.push(Timing {
+                filename: filename.to_string(),
+                micros: (now_micros() - started).max(0.0) as u64,
+            });
         }
+
+        self.papers.push(result?);
+        self.search_index = None;
+        Ok(&self.papers[self.papers.len() - 1])
     }
 
-    Ok(files)
+    /// Replace the loaded papers with `previous`, then process each of `files`
+    /// that has no paper with its filename or whose `contentHash` differs.
+    /// Changed papers keep their position. Returns the slugs processed.
+    ///
+    /// Papers whose source is unchanged are kept as they were, even if the
+    /// configuration has changed since; call `rerender_all` for that.
+    pub fn process_changed(
+        &mut self,
+        previous: Vec<Paper>,
+        files: &[SourceFile],
+    ) -> Result<Vec<String>, ProcessError> {
+        // Sections are not part of the JSON
+        let sections: Vec<_> = previous
+            .iter()
+            .map(|paper| self.parse_markdown_sections(&paper.content).0)
+            .collect();
+        self.papers = previous;
+        for (paper, sections) in self.papers.iter_mut().zip(sections) {
+            paper.sections = sections.into_iter().collect();
+        }
+        self.search_index = None;
+
+        let mut changed = Vec::new();
+        for file in files {
+            let hash = format!("{:016x}", content_hash(&file.filename, &file.content));
+            let existing = self
+                .papers
+                .iter()
+                .position(|paper| paper.filename == file.filename);
+            if existing.is_some_and(|idx| self.papers[idx].content_hash == hash) {
+                continue;
+            }
+
+            let replaced = existing.map(|idx| (idx, self.papers.remove(idx)));
+            let mtime = file.mtime.map(|mtime| mtime as i64);
+            if let Err(e) = self.process_with_mtime(&file.filename, &file.content, mtime) {
+                if let Some((idx, paper)) = replaced {
+                    self.papers.insert(idx, paper);
+                }
+                return Err(e);
+            }
+            let paper = self.papers.pop().unwrap();
+            changed.push(paper.slug.clone());
+            match replaced {
+                Some((idx, _)) => self.papers.insert(idx, paper),
+                None => self.papers.push(paper),
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Slugs added, removed or modified since `previous`. Added and modified
+    /// slugs are in processing order, removed ones in the order of `previous`.
+    pub fn slug_changes(&self, previous: &[Paper]) -> SlugChanges {
+        let mut changes = SlugChanges::default();
+        for paper in &self.papers {
+            match previous.iter().find(|old| old.slug == paper.slug) {
+                None => changes.added.push(paper.slug.clone()),
+                Some(old) if old.content_hash != paper.content_hash => {
+                    changes.modified.push(paper.slug.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        changes.removed = previous
+            .iter()
+            .filter(|old| self.paper(&old.slug).is_none())
+            .map(|old| old.slug.clone())
+            .collect();
+        changes
+    }
+
+    /// The files in a tar archive whose names end with one of `extensions`,
+    /// unless it exceeds the configured `max_archive_bytes`
+    pub fn tar_archive_files(
+        &self,
+        tar_data: &[u8],
+        extensions: &[&str],
+    ) -> Result<Vec<SourceFile>, ProcessError> {
+        read_tar_archive_limited(tar_data, extensions, self.config.max_archive_bytes)
+    }
+
+    /// Render code blocks in papers processed from now on with `renderer`,
+    /// in place of the default and of `set_code_line_numbers`. Mermaid fences
+    /// are still rendered as diagrams when that is enabled.
+    pub fn set_code_block_renderer(&mut self, renderer: impl CodeBlockRenderer + 'static) {
+        self.code_renderer = Some(Box::new(renderer));
+    }
+
+    /// Give headings in papers processed from now on the ids `id_fn` makes
+    /// from their text, in place of the built-in ones. A `heading_id_prefix`
+    /// is still added.
+    pub fn set_heading_id_function(&mut self, id_fn: impl Fn(&str) -> String + 'static) {
+        self.heading_id_fn = Some(Box::new(id_fn));
+    }
+
+    /// All processed papers, in processing order
+    pub fn papers(&self) -> &[Paper] {
+        &self.papers
+    }
+
+    /// Papers that appear in listings, feeds and search: all but hidden ones
+    pub fn listed_papers(&self) -> impl Iterator<Item = &Paper> {
+        self.papers.iter().filter(|paper| !paper.hidden)
+    }
+
+    /// The paper with the given slug, if any
+    pub fn paper(&self, slug: &str) -> Option<&Paper> {
+        self.papers.iter().find(|p| p.slug == slug)
+    }
+
+    /// The `limit` most recently updated non-draft papers, newest first, in
+    /// the same order as feeds
+    pub fn recent_papers(&self, limit: usize) -> Vec<&Paper> {
+        let mut papers = feed::entries(self.listed_papers());
+        papers.truncate(limit);
+        papers
+    }
+
+    /// Papers whose `lastUpdated` is between `start` and `end` seconds since
+    /// the Unix epoch inclusive, in processing order
+    pub fn papers_updated_between(&self, start: i64, end: i64) -> Vec<&Paper> {
+        self.listed_papers()
+            .filter(|paper| {
+                dates::parse_date(&paper.last_updated)
+                    .is_some_and(|date| (start..=end).contains(&date.unix_seconds()))
+            })
+            .collect()
+    }
+
+    /// Papers tagged `tag`, compared after the same normalization as
+    /// frontmatter tags, in processing order
+    pub fn papers_by_tag(&self, tag: &str) -> Vec<&Paper> {
+        let tag = normalize_tags(&[tag.to_string()]);
+        self.listed_papers()
+            .filter(|paper| {
+                paper
+                    .tags
+                    .as_ref()
+                    .is_some_and(|tags| tags.iter().any(|t| tag.contains(t)))
+            })
+            .collect()
+    }
+
+    /// Papers listing an author named `name`, ignoring case and spacing, in
+    /// processing order
+    pub fn papers_by_author(&self, name: &str) -> Vec<&Paper> {
+        let key = author_key(name);
+        self.listed_papers()
+            .filter(|paper| {
+                paper
+                    .authors
+                    .iter()
+                    .any(|author| author_key(&author.name) == key)
+            })
+            .collect()
+    }
+
+    /// Sorted, deduplicated tags across all papers
+    pub fn categories(&self) -> Vec<String> {
+        self.extract_categories()
+    }
+
+    /// Every author with their papers, sorted by `Author::sort_key`. Names
+    /// differing only in case or spacing are one author, shown with the
+    /// variant that has the most capitals. The first explicit `sortName` and
+    /// affiliation among the variants are used.
+    pub fn authors(&self) -> Vec<AuthorEntry> {
+        let mut groups: Vec<(String, Vec<&Author>, Vec<String>)> = Vec::new();
+        for paper in self.listed_papers() {
+            for author in &paper.authors {
+                let key = author_key(&author.name);
+                let idx = match groups.iter().position(|(k, _, _)| *k == key) {
+                    Some(idx) => idx,
+                    None => {
+                        groups.push((key, Vec::new(), Vec::new()));
+                        groups.len() - 1
+                    }
+                };
+                let (_, variants, papers) = &mut groups[idx];
+                variants.push(author);
+                if !papers.contains(&paper.slug) {
+                    papers.push(paper.slug.clone());
+                }
+            }
+        }
+
+        let mut entries: Vec<AuthorEntry> = groups
+            .into_iter()
+            .map(|(_, variants, papers)| {
+                let capitals = |name: &str| name.chars().filter(|c| c.is_uppercase()).count();
+                let name = variants
+                    .iter()
+                    .map(|author| normalize_name(&author.name))
+                    .reduce(|best, name| {
+                        if capitals(&name) > capitals(&best) {
+                            name
+                        } else {
+                            best
+                        }
+                    })
+                    .unwrap_or_default();
+                let display = Author {
+                    sort_name: variants.iter().find_map(|author| author.sort_name.clone()),
+                    affiliation: variants
+                        .iter()
+                        .find_map(|author| author.affiliation.clone()),
+                    name,
+                };
+                AuthorEntry {
+                    sort_name: display.sort_key(),
+                    name: display.name,
+                    affiliation: display.affiliation,
+                    papers,
+                }
+            })
+            .collect();
+
+        entries.sort_by(|a, b| {
+            a.sort_name
+                .to_lowercase()
+                .cmp(&b.sort_name.to_lowercase())
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        entries
+    }
+
+    /// A paper's sections in document order, with byte offsets into its `content`
+    pub fn section_offsets(&self, slug: &str) -> Option<Vec<Section>> {
+        self.paper(slug)
+            .map(|paper| self.locate_sections(&paper.content))
+    }
+
+    /// The HTML of the section of a paper starting at the heading with id
+    /// `heading_id`; see `get_section_html_by_id`
+    pub fn section_html_by_id(&self, slug: &str, heading_id: &str) -> Option<String> {
+        let paper = self.paper(slug)?;
+        if paper.redirect_url.is_some() {
+            return None;
+        }
+        let prefix = self.heading_prefix(slug);
+        self.with_body_events(&paper.content, &paper.title, prefix.as_deref(), |body| {
+            let idx = body
+                .headings
+                .iter()
+                .position(|heading| heading.id == heading_id)?;
+            let level = body.headings[idx].level;
+            let end = body.headings[idx + 1..]
+                .iter()
+                .zip(&body.heading_starts[idx + 1..])
+                .find(|(heading, _)| heading.level <= level)
+                .map_or(body.events.len(), |(_, &start)| start);
+            let mut html_output = String::new();
+            html::push_html(
+                &mut html_output,
+                body.events[body.heading_starts[idx]..end].iter().cloned(),
+            );
+            Some(html_output)
+        })
+    }
+
+    /// The `top_n` most frequent terms in a paper's prose, most frequent first
+    pub fn word_frequencies(&self, slug: &str, top_n: usize) -> Option<Vec<TermCount>> {
+        self.paper(slug)
+            .map(|paper| search::term_frequencies(&markdown_to_text(&paper.content), top_n))
+    }
+
+    /// A paper's card blurb; see `get_card_html`
+    pub fn card_html(&self, slug: &str) -> Option<String> {
+        let paper = self.paper(slug)?;
+        let mut html = String::from("<div class=\"paper-card\">");
+        html.push_str(&format!(
+            "<p class=\"card-summary\">{}</p>",
+            feed::escape(&markdown_to_text(&paper.summary))
+        ));
+        let abstract_text = markdown_to_text(&paper.abstract_text);
+        if !abstract_text.is_empty() {
+            html.push_str(&format!(
+                "<p class=\"card-abstract\">{}</p>",
+                feed::escape(first_sentence(&abstract_text))
+            ));
+        }
+        html.push_str("</div>");
+        Some(html)
+    }
+
+    /// Up to `limit` tags for a paper that it does not have yet, best first;
+    /// see `suggest_tags`
+    pub fn tag_suggestions(&self, slug: &str, limit: usize) -> Option<Vec<String>> {
+        let paper = self.paper(slug)?;
+        let terms = self.word_frequencies(slug, usize::MAX)?;
+        let own: Vec<String> = paper
+            .tags
+            .iter()
+            .flatten()
+            .map(|tag| tag.to_lowercase())
+            .collect();
+        let others: Vec<Vec<&String>> = self
+            .listed_papers()
+            .filter(|other| other.slug != paper.slug)
+            .filter_map(|other| other.tags.as_ref())
+            .map(|tags| tags.iter().collect())
+            .filter(|tags: &Vec<&String>| !tags.is_empty())
+            .collect();
+
+        if others.is_empty() {
+            return Some(
+                terms
+                    .into_iter()
+                    .map(|term| term.term)
+                    .filter(|term| !own.contains(term))
+                    .take(limit)
+                    .collect(),
+            );
+        }
+
+        let max_count = terms.first().map_or(1, |term| term.count) as f64;
+        let frequency = |word: &str| {
+            terms
+                .iter()
+                .find(|term| term.term == word)
+                .map_or(0.0, |term| term.count as f64 / max_count)
+        };
+
+        // Score each tag once, under the spelling first seen
+        let mut scores: Vec<(&String, f64)> = Vec::new();
+        for tags in &others {
+            for &tag in tags {
+                let key = tag.to_lowercase();
+                if own.contains(&key) || scores.iter().any(|(seen, _)| seen.to_lowercase() == key) {
+                    continue;
+                }
+                let words = search::tokenize(tag, TokenizerOptions::default());
+                let mentions = words.iter().map(|word| frequency(word)).sum::<f64>()
+                    / words.len().max(1) as f64;
+                let together = others
+                    .iter()
+                    .filter(|tags| tags.iter().any(|t| t.to_lowercase() == key))
+                    .map(|tags| {
+                        tags.iter()
+                            .filter(|t| own.contains(&t.to_lowercase()))
+                            .count()
+                    })
+                    .sum::<usize>() as f64;
+                let co_occurrence = together / own.len().max(1) as f64;
+                scores.push((tag, mentions + co_occurrence));
+            }
+        }
+
+        scores.retain(|&(_, score)| score > 0.0);
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        Some(
+            scores
+                .into_iter()
+                .take(limit)
+                .map(|(tag, _)| tag.clone())
+                .collect(),
+        )
+    }
+
+    /// All non-draft papers rendered into one HTML fragment, as
+    /// `render_combined_html` describes
+    pub fn combined_html(&self, order: PaperOrder) -> String {
+        let mut papers: Vec<&Paper> = self.listed_papers().filter(|p| !p.draft).collect();
+        match order {
+            PaperOrder::Title => papers.sort_by_key(|paper| paper.title.to_lowercase()),
+            PaperOrder::Date => papers.sort_by_key(|paper| {
+                std::cmp::Reverse(
+                    dates::parse_date(&paper.last_updated).map(|date| date.unix_seconds()),
+                )
+            }),
+            PaperOrder::Slug => papers.sort_by(|a, b| a.slug.cmp(&b.slug)),
+        }
+
+        let mut toc = String::from("<nav class=\"combined-toc\">\n<ol>\n");
+        let mut body = String::new();
+        for paper in papers {
+            let rendered = match paper.redirect_url {
+                Some(_) => Rendered::default(),
+                None => self.render_body_prefixed(&paper.content, &paper.title, Some(&paper.slug)),
+            };
+            let slug = feed::escape(&paper.slug);
+            let title = feed::escape(&paper.title);
+
+            toc.push_str(&format!("<li><a href=\"#{}\">{}</a>", slug, title));
+            let sections: Vec<&Heading> = rendered
+                .headings
+                .iter()
+                .filter(|heading| heading.level == self.config.section_level)
+                .collect();
+            if !sections.is_empty() {
+                toc.push_str("\n<ol>\n");
+                for heading in sections {
+                    toc.push_str(&format!(
+                        "<li><a href=\"#{}\">{}</a></li>\n",
+                        feed::escape(&heading.id),
+                        feed::escape(&heading.text)
+                    ));
+                }
+                toc.push_str("</ol>\n");
+            }
+            toc.push_str("</li>\n");
+
+            body.push_str("<hr class=\"paper-separator\">\n");
+            body.push_str(&format!(
+                "<article id=\"{}\" class=\"combined-paper\">\n<h1>{}</h1>\n{}</article>\n",
+                slug, title, rendered.html
+            ));
+        }
+        toc.push_str("</ol>\n</nav>\n");
+        toc + &body
+    }
+
+    /// Every problem in the loaded papers, paper by paper in processing order
+    pub fn lint(&self) -> LintReport {
+        let broken_links = self.broken_links();
+        let mut issues = Vec::new();
+        for paper in &self.papers {
+            for warning in &paper.warnings {
+                let severity = if paper.errors.contains(warning) {
+                    Severity::Error
+                } else {
+                    Severity::Warning
+                };
+                issues.push(LintIssue {
+                    severity,
+                    slug: paper.slug.clone(),
+                    message: warning.clone(),
+                });
+            }
+            // Broken `#anchor` links are already among the warnings
+            for broken in broken_links
+                .iter()
+                .filter(|broken| broken.slug == paper.slug && broken.link.starts_with("[["))
+            {
+                issues.push(LintIssue {
+                    severity: Severity::Error,
+                    slug: paper.slug.clone(),
+                    message: format!("Broken link {}", broken.link),
+                });
+            }
+        }
+
+        let error_count = issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .count();
+        LintReport {
+            ok: error_count == 0,
+            error_count,
+            warning_count: issues.len() - error_count,
+            issues,
+        }
+    }
+
+    /// Hex hash of the sorted `contentHash`es of all papers
+    pub fn collection_etag(&self) -> String {
+        let mut hashes: Vec<&str> = self
+            .papers
+            .iter()
+            .map(|paper| paper.content_hash.as_str())
+            .collect();
+        hashes.sort_unstable();
+        format!("{:016x}", fnv1a_64(hashes.join("\n").as_bytes()))
+    }
+
+    /// `js_assert_no_broken_links` for Rust callers
+    pub fn assert_no_broken_links(&self) -> Result<(), ProcessError> {
+        let broken = self.broken_links();
+        if broken.is_empty() {
+            return Ok(());
+        }
+        Err(ProcessError::BrokenLinks(broken))
+    }
+
+    /// Links in any paper that point at no paper or heading: `[[slug]]`
+    /// references to unknown papers, `[[slug#anchor]]` references to unknown
+    /// headings and in-page `#anchor` links
+    pub fn broken_links(&self) -> Vec<BrokenLink> {
+        let has_heading =
+            |paper: &Paper, anchor: &str| self.anchor_matches(&paper.slug, &paper.headings, anchor);
+        let mut broken = Vec::new();
+
+        for paper in &self.papers {
+            let events = render::merge_text(Parser::new_ext(&paper.content, render::options()));
+            for reference in links::cross_refs(&events) {
+                let found = self.paper(&reference.slug).is_some_and(|target| {
+                    reference
+                        .anchor
+                        .as_deref()
+                        .is_none_or(|anchor| has_heading(target, anchor))
+                });
+                if !found {
+                    broken.push(BrokenLink {
+                        slug: paper.slug.clone(),
+                        link: reference.to_markdown(),
+                    });
+                }
+            }
+            for anchor in render::scan_body(&events).anchor_links {
+                if !has_heading(paper, &anchor) {
+                    broken.push(BrokenLink {
+                        slug: paper.slug.clone(),
+                        link: format!("#{}", anchor),
+                    });
+                }
+            }
+        }
+
+        broken
+    }
+
+    /// Every series sorted by name, with its papers sorted by `series_order`.
+    /// Papers without an order come last, in processing order.
+    pub fn series(&self) -> Vec<Series> {
+        let mut series: BTreeMap<&str, Vec<SeriesEntry>> = BTreeMap::new();
+        for paper in self.listed_papers() {
+            if let Some(ref name) = paper.series {
+                series.entry(name).or_default().push(SeriesEntry {
+                    slug: paper.slug.clone(),
+                    title: paper.title.clone(),
+                    order: paper.series_order,
+                });
+            }
+        }
+
+        series
+            .into_iter()
+            .map(|(name, mut papers)| {
+                papers.sort_by_key(|entry| (entry.order.is_none(), entry.order));
+                Series {
+                    name: name.to_string(),
+                    papers,
+                }
+            })
+            .collect()
+    }
+
+    /// The slugs around `slug` in its series, or `None` if there is no
+    /// such paper
+    pub fn series_neighbors(&self, slug: &str) -> Option<SeriesNeighbors> {
+        let paper = self.paper(slug)?;
+        let Some(ref name) = paper.series else {
+            return Some(SeriesNeighbors::default());
+        };
+
+        let series = self
+            .series()
+            .into_iter()
+            .find(|series| &series.name == name)?;
+        let idx = series.papers.iter().position(|entry| entry.slug == slug)?;
+        Some(SeriesNeighbors {
+            previous: idx
+                .checked_sub(1)
+                .map(|prev| series.papers[prev].slug.clone()),
+            next: series.papers.get(idx + 1).map(|entry| entry.slug.clone()),
+        })
+    }
+
+    /// Every category with its slug, in category order
+    pub fn category_slugs(&self) -> Vec<Category> {
+        self.categories()
+            .into_iter()
+            .map(|display| Category {
+                slug: slugify(&display),
+                display,
+            })
+            .collect()
+    }
+
+    /// Every category with its hue, in category order
+    pub fn tag_colors(&self) -> Vec<TagColor> {
+        self.categories()
+            .into_iter()
+            .map(|tag| TagColor {
+                hue: tag_hue(&tag),
+                tag,
+            })
+            .collect()
+    }
+
+    /// Sorted, deduplicated statuses across all papers, omitting papers
+    /// without one
+    pub fn statuses(&self) -> Vec<String> {
+        let statuses: std::collections::BTreeSet<&String> = self
+            .listed_papers()
+            .filter_map(|paper| paper.status.as_ref())
+            .collect();
+        statuses.into_iter().cloned().collect()
+    }
+
+    /// Totals across the listed papers. Tags are counted after normalization
+    /// and authors after merging the same case and spacing variants as
+    /// `authors`.
+    pub fn corpus_stats(&self) -> CorpusStats {
+        let mut stats = CorpusStats::default();
+        let mut tags = std::collections::BTreeSet::new();
+        let mut authors = std::collections::BTreeSet::new();
+        let mut first: Option<(i64, &str)> = None;
+        let mut last: Option<(i64, &str)> = None;
+
+        for paper in self.listed_papers() {
+            stats.papers += 1;
+            stats.words += paper.word_count;
+            stats.reading_minutes += paper.reading_minutes;
+            if paper.draft {
+                stats.drafts += 1;
+            } else {
+                stats.published += 1;
+            }
+            tags.extend(paper.tags.iter().flatten());
+            authors.extend(paper.authors.iter().map(|author| author_key(&author.name)));
+
+            if let Some(date) = dates::parse_date(&paper.last_updated) {
+                let updated = (date.unix_seconds(), paper.last_updated.as_str());
+                if first.is_none_or(|(secs, _)| updated.0 < secs) {
+                    first = Some(updated);
+                }
+                if last.is_none_or(|(secs, _)| updated.0 > secs) {
+                    last = Some(updated);
+                }
+            }
+        }
+
+        stats.tags = tags.len();
+        stats.authors = authors.len();
+        stats.first_updated = first.map(|(_, date)| date.to_string());
+        stats.last_updated = last.map(|(_, date)| date.to_string());
+        stats
+    }
+
+    /// Recorded processing times, slowest first
+    pub fn timings(&self) -> Vec<Timing> {
+        let mut timings = self.timings.clone();
+        timings.sort_by(|a, b| {
+            b.micros
+                .cmp(&a.micros)
+                .then_with(|| a.filename.cmp(&b.filename))
+        });
+        timings
+    }
+
+    /// Papers matching `query` by substring, best first
+    pub fn search_results(&self, query: &str) -> Vec<SearchHit> {
+        self.search_index().search(&self.papers, query)
+    }
+
+    /// Papers matching `query` within `max_distance` edits per term, best first
+    pub fn fuzzy_search_results(&self, query: &str, max_distance: usize) -> Vec<SearchHit> {
+        self.search_index()
+            .search_fuzzy(&self.papers, query, max_distance)
+    }
+}
+
+impl PaperProcessor {
+    fn to_json<T: Serialize + ?Sized>(&self, value: &T) -> serde_json::Result<String> {
+        json::to_string(value, self.config.pretty)
+    }
+
+    /// Use the built search index, or build a throwaway one if none exists yet
+    fn search_index(&self) -> Cow<'_, SearchIndex> {
+        match &self.search_index {
+            Some(index) => Cow::Borrowed(index),
+            None => Cow::Owned(SearchIndex::build(&self.papers, self.config.tokenizer)),
+        }
+    }
+
+    /// `slug`, or the first of `slug-2`, `slug-3`, ... no loaded paper uses
+    fn unique_slug(&self, slug: String) -> String {
+        if self.paper(&slug).is_none() {
+            return slug;
+        }
+        (2..)
+            .map(|n| format!("{}-{}", slug, n))
+            .find(|candidate| self.paper(candidate).is_none())
+            .unwrap()
+    }
+
+    /// Render `paper` again from `content` with the current configuration
+    fn rerender(&self, paper: &Paper, content: &str) -> Rerendered {
+        let (sections, section_names) = self.parse_markdown_sections(content);
+        let rendered = match paper.redirect_url {
+            Some(_) => Rendered::default(),
+            None => self.render_body(content, &paper.slug, &paper.title),
+        };
+        let toc = self.choose_toc(
+            &sections,
+            paper.toc_frontmatter.as_deref(),
+            &rendered.headings,
+        );
+        let is_stub = paper.redirect_url.is_none() && self.is_stub(rendered.stats.word_count);
+        let render_warnings = self.render_warnings(&paper.slug, &rendered, is_stub);
+        Rerendered {
+            rendered,
+            sections,
+            section_names,
+            toc,
+            is_stub,
+            render_warnings,
+        }
+    }
+
+    /// Whether a body of `word_count` words is under the stub threshold
+    fn is_stub(&self, word_count: usize) -> bool {
+        self.config.stub_word_threshold > 0 && word_count < self.config.stub_word_threshold
+    }
+
+    /// Warnings about the rendered body of the paper `slug`: anchors that
+    /// match no heading, skipped heading levels, images without alt text
+    /// and, if `is_stub`, the paper being a stub
+    fn render_warnings(&self, slug: &str, rendered: &Rendered, is_stub: bool) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for anchor in &rendered.stats.anchor_links {
+            if !self.anchor_matches(slug, &rendered.headings, anchor) {
+                warn(
+                    &mut warnings,
+                    format!("Link to #{} does not match any heading", anchor),
+                );
+            }
+        }
+
+        for pair in rendered.headings.windows(2) {
+            if pair[1].level > pair[0].level + 1 {
+                warn(
+                    &mut warnings,
+                    format!(
+                        "Heading `{}` skips from level {} to level {}",
+                        pair[1].text, pair[0].level, pair[1].level
+                    ),
+                );
+            }
+        }
+
+        if !rendered.stats.images_missing_alt.is_empty() {
+            warn(
+                &mut warnings,
+                format!(
+                    "Images without alt text: {}",
+                    rendered.stats.images_missing_alt.join(", ")
+                ),
+            );
+        }
+
+        if is_stub {
+            warn(
+                &mut warnings,
+                format!(
+                    "Paper has {} words, fewer than the stub threshold of {}",
+                    rendered.stats.word_count, self.config.stub_word_threshold
+                ),
+            );
+        }
+        warnings
+    }
+
+    /// Fail with `error` in strict mode, otherwise record it as a warning
+    /// and in `errors`
+    fn check(
+        &self,
+        warnings: &mut Vec<String>,
+        errors: &mut Vec<String>,
+        error: ProcessError,
+    ) -> Result<(), ProcessError> {
+        if self.config.strict {
+            return Err(error);
+        }
+        warn(warnings, error.to_string());
+        errors.push(error.to_string());
+        Ok(())
+    }
+
+    fn process_single_paper(
+        &self,
+        filename: &str,
+        content: &str,
+        mtime: Option<i64>,
+    ) -> Result<Paper, ProcessError> {
+        if let Some(limit) = self.config.max_content_bytes {
+            if content.len() > limit {
+                return Err(ProcessError::TooLarge {
+                    size: content.len(),
+                    limit,
+                });
+            }
+        }
+
+        let mut warnings = Vec::new();
+        let mut errors = Vec::new();
+        let (metadata, markdown) = self.parse_frontmatter(content, &mut warnings, &mut errors)?;
+        let had_frontmatter = metadata.is_some();
+        let metadata = metadata.unwrap_or_default();
+        let (sections, section_names) = self.parse_markdown_sections(&markdown);
+
+        let slug = match metadata.slug.as_deref().map(slugify) {
+            Some(slug) if !slug.is_empty() => {
+                if let Some(existing) = self.paper(&slug) {
+                    self.check(
+                        &mut warnings,
+                        &mut errors,
+                        ProcessError::DuplicateSlug {
+                            slug: slug.clone(),
+                            filename: filename.to_string(),
+                            existing: existing.filename.clone(),
+                        },
+                    )?;
+                }
+                self.unique_slug(slug)
+            }
+            _ => self.unique_slug(slug_from_filename(filename)),
+        };
+        let title = metadata.title.clone().or_else(|| {
+            self.config
+                .title_from_heading
+                .then(|| {
+                    extract_headings(&markdown)
+                        .into_iter()
+                        .find(|h| h.level == 1)
+                })
+                .flatten()
+                .map(|heading| heading.text)
+        });
+        // A title taken from the first heading is not missing
+        if title.is_none() {
+            self.check(
+                &mut warnings,
+                &mut errors,
+                ProcessError::MissingTitle(filename.to_string()),
+            )?;
+        }
+        let title = title.unwrap_or_else(|| slug.clone());
+
+        let rendered = match metadata.redirect {
+            Some(_) => Rendered::default(),
+            None => self.render_body(&markdown, &slug, &title),
+        };
+        let toc = self.choose_toc(&sections, metadata.toc.as_deref(), &rendered.headings);
+
+        let is_stub = metadata.redirect.is_none() && self.is_stub(rendered.stats.word_count);
+        let render_warnings = self.render_warnings(&slug, &rendered, is_stub);
+
+        let (last_updated, last_updated_source) =
+            self.read_last_updated(&metadata, &mut warnings, &mut errors)?;
+
+        let authors = self.parse_authors(metadata.authors, &mut warnings, &mut errors)?;
+        let tags = metadata.tags.as_deref().map(normalize_tags);
+        let tags_raw = metadata.tags.filter(|raw| Some(raw) != tags.as_ref());
+        for tag in tags.iter().flatten() {
+            if !is_url_safe(tag) {
+                warn(
+                    &mut warnings,
+                    format!(
+                        "Tag `{}` is not URL-safe; its slug is `{}`",
+                        tag,
+                        slugify(tag)
+                    ),
+                );
+            }
+        }
+
+        let abstract_text = sections.get("abstract").cloned().unwrap_or_default();
+        let abstract_short = self
+            .config
+            .abstract_max_chars
+            .and_then(|max_chars| truncate_words(&abstract_text, max_chars));
+        let abstract_truncated = abstract_short.is_some();
+        let meta_description = [
+            sections.get("abstract"),
+            metadata.summary.as_ref(),
+            sections.get("summary"),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|text| markdown_to_text(text))
+        .chain(std::iter::once_with(|| markdown_to_text(&markdown)))
+        .find(|text| !text.is_empty())
+        .map(|text| {
+            let sentence = first_sentence(&text);
+            truncate_words(sentence, 155).unwrap_or_else(|| sentence.to_string())
+        })
+        .unwrap_or_default();
+
+        let paper = Paper {
+            title,
+            slug,
+            filename: filename.to_string(),
+            summary: metadata
+                .summary
+                .clone()
+                .or_else(|| sections.get("summary").cloned())
+                .unwrap_or_else(|| {
+                    let lang = ["lang", "language"]
+                        .iter()
+                        .find_map(|key| metadata.extra.get(*key).and_then(|v| v.as_str()));
+                    self.config.default_summary(lang).to_string()
+                }),
+            abstract_text: abstract_short.unwrap_or_else(|| abstract_text.clone()),
+            meta_description,
+            abstract_truncated,
+            abstract_full: abstract_truncated.then_some(abstract_text),
+            toc,
+            toc_frontmatter: metadata.toc,
+            content: markdown.clone(),
+            headings: rendered.headings,
+            figures: rendered.stats.figures,
+            html: rendered.html,
+            last_updated_source,
+            last_updated: last_updated.unwrap_or_else(|| match self.config.last_updated_fallback {
+                LastUpdatedFallback::Now => now_iso(),
+                LastUpdatedFallback::Mtime => mtime.map_or_else(now_iso, dates::format_iso_millis),
+                LastUpdatedFallback::Empty => String::new(),
+                LastUpdatedFallback::Epoch => dates::format_iso_millis(0),
+            }),
+            authors,
+            tags,
+            tags_raw,
+            status: metadata.status,
+            has_math: rendered.stats.has_math,
+            has_code: rendered.stats.has_code,
+            tasks_done: rendered.stats.tasks_done,
+            tasks_total: rendered.stats.tasks_total,
+            word_count: rendered.stats.word_count,
+            reading_minutes: reading_minutes(rendered.stats.word_count),
+            is_stub,
+            cover_image: metadata.cover.or(rendered.stats.first_image),
+            had_frontmatter,
+            hidden: metadata.hidden.unwrap_or(false),
+            noindex: metadata.noindex.unwrap_or(false),
+            canonical_url: metadata.canonical,
+            redirect_url: metadata.redirect,
+            layout: metadata.layout,
+            classes: metadata.classes,
+            draft: metadata.draft.unwrap_or(false),
+            series: metadata.series,
+            series_order: metadata.series_order,
+            content_hash: format!("{:016x}", content_hash(filename, content)),
+            sections: sections.into_iter().collect(),
+            section_names,
+            warnings: warnings.iter().chain(&render_warnings).cloned().collect(),
+            source_warnings: warnings,
+            render_warnings,
+            errors,
+            extra: metadata.extra,
+        };
+
+        Ok(paper)
+    }
+
+    /// The first of the configured `date_keys` whose value reads in one of
+    /// the configured `date_formats`, with where it came from. ISO dates are
+    /// kept as written and others become `YYYY-MM-DD`. When no value reads,
+    /// the first one found is kept as written and reported as invalid.
+    fn read_last_updated(
+        &self,
+        metadata: &PaperMetadata,
+        warnings: &mut Vec<String>,
+        errors: &mut Vec<String>,
+    ) -> Result<(Option<String>, Option<DateSource>), ProcessError> {
+        let mut unreadable = None;
+        for key in &self.config.date_keys {
+            let value = if key == "lastUpdated" {
+                metadata.last_updated.clone()
+            } else {
+                metadata.extra.get(key).and_then(|value| match value {
+                    serde_json::Value::String(value) => Some(value.clone()),
+                    _ => None,
+                })
+            };
+            let Some(value) = value else {
+                continue;
+            };
+
+            for &format in &self.config.date_formats {
+                let normalized = match format {
+                    DateFormat::Iso => dates::parse_date(&value).map(|_| value.clone()),
+                    DateFormat::Dmy => {
+                        dates::parse_numeric_date(&value, true).map(|d| dates::format_date(&d))
+                    }
+                    DateFormat::Mdy => {
+                        dates::parse_numeric_date(&value, false).map(|d| dates::format_date(&d))
+                    }
+                };
+                if let Some(normalized) = normalized {
+                    let source = DateSource {
+                        key: key.clone(),
+                        format,
+                    };
+                    return Ok((Some(normalized), Some(source)));
+                }
+            }
+            // Keep looking: a later key may still hold a readable date
+            unreadable.get_or_insert(value);
+        }
+        if let Some(ref value) = unreadable {
+            self.check(warnings, errors, ProcessError::InvalidDate(value.clone()))?;
+        }
+        Ok((unreadable, None))
+    }
+
+    fn parse_frontmatter(
+        &self,
+        content: &str,
+        warnings: &mut Vec<String>,
+        errors: &mut Vec<String>,
+    ) -> Result<(Option<PaperMetadata>, String), ProcessError> {
+        let frontmatter_regex = Regex::new(r"^---\s*\n([\s\S]*?)\n---\s*\n([\s\S]*)$").unwrap();
+
+        if let Some(captures) = frontmatter_regex.captures(content) {
+            let yaml_content = &captures[1];
+            let markdown_content = &captures[2];
+
+            // Outside strict mode a paper with broken frontmatter is kept
+            // with empty metadata rather than lost
+            let parsed =
+                frontmatter::parse_mapping(yaml_content).and_then(|(mut mapping, duplicates)| {
+                    frontmatter::apply_aliases(&mut mapping, &self.config.field_aliases);
+                    let metadata = serde_yaml::from_value(serde_yaml::Value::Mapping(mapping))?;
+                    Ok((metadata, duplicates))
+                });
+            let metadata = match parsed {
+                Ok((metadata, duplicates)) => {
+                    for key in duplicates {
+                        self.check(warnings, errors, ProcessError::DuplicateKey(key))?;
+                    }
+                    metadata
+                }
+                Err(e) => {
+                    // The YAML starts on the line after the opening `---`
+                    self.check(warnings, errors, ProcessError::yaml(e, 2))?;
+                    PaperMetadata::default()
+                }
+            };
+            Ok((Some(metadata), markdown_content.to_string()))
+        } else {
+            self.check(warnings, errors, ProcessError::MissingFrontmatter)?;
+            Ok((None, content.to_string()))
+        }
+    }
+
+    /// Read `authors` as a list of `{name, affiliation}` objects. Outside
+    /// strict mode plain name strings are accepted and unusable entries dropped.
+    fn parse_authors(
+        &self,
+        value: Option<serde_yaml::Value>,
+        warnings: &mut Vec<String>,
+        errors: &mut Vec<String>,
+    ) -> Result<Vec<Author>, ProcessError> {
+        let Some(value) = value else {
+            return Ok(Vec::new());
+        };
+
+        if let Ok(authors) = serde_yaml::from_value::<Vec<Author>>(value.clone()) {
+            return Ok(authors);
+        }
+
+        let reason = match value {
+            serde_yaml::Value::Sequence(_) => "each author needs at least a `name`",
+            _ => "expected a list of authors",
+        };
+        self.check(
+            warnings,
+            errors,
+            ProcessError::MalformedAuthors(reason.to_string()),
+        )?;
+
+        let items = match value {
+            serde_yaml::Value::Sequence(items) => items,
+            other => vec![other],
+        };
+        Ok(items
+            .into_iter()
+            .filter_map(|item| match item {
+                serde_yaml::Value::String(name) => Some(Author {
+                    name,
+                    affiliation: None,
+                    sort_name: None,
+                }),
+                other => serde_yaml::from_value(other).ok(),
+            })
+            .collect())
+    }
+
+    /// The `##` sections of `markdown` keyed by name, and their names in
+    /// document order. A repeated heading keeps its first position.
+    fn parse_markdown_sections(&self, markdown: &str) -> (HashMap<String, String>, Vec<String>) {
+        let mut sections = HashMap::new();
+        let mut names = Vec::new();
+        for section in self.locate_sections(markdown) {
+            if !sections.contains_key(&section.name) {
+                names.push(section.name.clone());
+            }
+            sections.insert(section.name, section.text);
+        }
+        (sections, names)
+    }
+
+    /// Find the `##` sections of `markdown` with their byte ranges
+    fn locate_sections(&self, markdown: &str) -> Vec<Section> {
+        let mut sections = Vec::new();
+        let lines: Vec<&str> = markdown.lines().collect();
+        // Byte offset of the line and name of the section being collected
+        let mut current_section: Option<(usize, String)> = None;
+        let mut current_content = Vec::new();
+        let mut in_fence = false;
+        let mut i = 0;
+        let level = self.config.section_level;
+        let atx_prefix = format!("{} ", "#".repeat(level as usize));
+
+        while i < lines.len() {
+            let line = lines[i];
+            if is_fence(line) {
+                in_fence = !in_fence;
+            }
+
+            // Sections start at headings of the configured level, by default
+            // either `## Title` or a setext `Title` underlined with `---`
+            let start = line.as_ptr() as usize - markdown.as_ptr() as usize;
+            let heading = if in_fence {
+                None
+            } else if let Some(heading) = line.strip_prefix(&atx_prefix) {
+                Some(heading)
+            } else if lines.get(i + 1).and_then(|next| setext_level(line, next)) == Some(level) {
+                i += 1;
+                Some(line)
+            } else {
+                None
+            };
+
+            if let Some(heading) = heading {
+                // Save previous section if exists
+                if let Some((section_start, name)) = current_section.take() {
+                    sections.push(Section {
+                        name,
+                        start: section_start,
+                        end: start,
+                        text: current_content.join("\n").trim().to_string(),
+                    });
+                }
+                // Start new section
+                current_section = Some((start, heading.trim().to_lowercase()));
+                current_content.clear();
+            } else if current_section.is_some() {
+                current_content.push(line);
+            }
+            i += 1;
+        }
+
+        // Save final section
+        if let Some((start, name)) = current_section {
+            sections.push(Section {
+                name,
+                start,
+                end: markdown.len(),
+                text: current_content.join("\n").trim().to_string(),
+            });
+        }
+
+        sections.retain(|section| !self.config.excluded_sections.contains(&section.name));
+        sections
+    }
+
+    /// The `toc` given by the configured `toc_source`
+    fn choose_toc(
+        &self,
+        sections: &HashMap<String, String>,
+        frontmatter: Option<&[String]>,
+        headings: &[Heading],
+    ) -> Vec<String> {
+        let manual = self.extract_toc(sections);
+        let auto: Vec<String> = headings
+            .iter()
+            .filter(|heading| {
+                heading.level == self.config.section_level
+                    && !self
+                        .config
+                        .toc_headings
+                        .contains(&heading.text.trim().to_lowercase())
+            })
+            .map(|heading| heading.text.clone())
+            .collect();
+        select_toc(
+            &self.config.toc_source,
+            &manual,
+            frontmatter.unwrap_or_default(),
+            &auto,
+        )
+    }
+
+    fn extract_toc(&self, sections: &HashMap<String, String>) -> Vec<String> {
+        let item_regex = Regex::new(r"^\d+\.\s+\*\*(.*?)\*\*").unwrap();
+
+        self.config
+            .toc_headings
+            .iter()
+            .find_map(|heading| sections.get(heading))
+            .map(|toc_section| {
+                toc_section
+                    .lines()
+                    .filter_map(|line| item_regex.captures(line).map(|cap| cap[1].to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Render a paper body, dropping a leading `# Title` that repeats the
+    /// title shown above it when configured to
+    fn render_body(&self, markdown: &str, slug: &str, title: &str) -> Rendered {
+        let prefix = self.heading_prefix(slug);
+        self.render_body_prefixed(markdown, title, prefix.as_deref())
+    }
+
+    /// The configured heading id prefix for the paper `slug`
+    fn heading_prefix(&self, slug: &str) -> Option<String> {
+        self.config
+            .heading_id_prefix
+            .as_ref()
+            .map(|prefix| prefix.replace("{slug}", slug))
+    }
+
+    /// Whether `anchor`, as written after `#` in markdown, is the id of one of
+    /// `headings` in the paper `slug` once the configured prefix is added
+    fn anchor_matches(&self, slug: &str, headings: &[Heading], anchor: &str) -> bool {
+        let id = match self.heading_prefix(slug) {
+            Some(prefix) => format!("{}__{}", prefix, anchor),
+            None => anchor.to_string(),
+        };
+        headings.iter().any(|heading| heading.id == id)
+    }
+
+    /// The function heading ids are made with, if any, and whether repeated
+    /// ids are numbered
+    fn heading_id_fn(&self) -> (Option<&render::HeadingIdFn>, bool) {
+        let id_fn: Option<&render::HeadingIdFn> =
+            match (self.config.heading_id_style, &self.heading_id_fn) {
+                (HeadingIdStyle::None, _) => None,
+                (_, Some(id_fn)) => Some(id_fn.as_ref()),
+                (HeadingIdStyle::Kebab, None) => Some(&render::heading_id),
+                (HeadingIdStyle::GitHub, None) => Some(&render::github_heading_id),
+            };
+        (
+            id_fn,
+            self.config.heading_id_style == HeadingIdStyle::GitHub,
+        )
+    }
+
+    /// `render_body` with heading ids, in-page `#anchor` links and footnotes
+    /// prefixed `{prefix}__`
+    fn render_body_prefixed(&self, markdown: &str, title: &str, prefix: Option<&str>) -> Rendered {
+        self.with_body_events(markdown, title, prefix, |body| body.into_rendered())
+    }
+
+    /// Call `f` with the events `render_body_prefixed` renders
+    fn with_body_events<R>(
+        &self,
+        markdown: &str,
+        title: &str,
+        prefix: Option<&str>,
+        f: impl FnOnce(BodyEvents<'_>) -> R,
+    ) -> R {
+        let leading_h1 = self
+            .config
+            .strip_title_heading
+            .then(|| render::leading_h1(markdown))
+            .flatten()
+            .filter(|(text, _)| text.trim().eq_ignore_ascii_case(title.trim()));
+        match leading_h1 {
+            Some((_, range)) => {
+                let body = format!("{}{}", &markdown[..range.start], &markdown[range.end..]);
+                self.with_events(&body, prefix, f)
+            }
+            None => self.with_events(markdown, prefix, f),
+        }
+    }
+
+    /// Render markdown with heading ids, in-page `#anchor` links and
+    /// footnotes prefixed `{prefix}__`, so all three still match each other
+    fn markdown_to_html(&self, markdown: &str, prefix: Option<&str>) -> Rendered {
+        self.with_events(markdown, prefix, |body| body.into_rendered())
+    }
+
+    /// Call `f` with the events `markdown_to_html` renders, after every
+    /// transform
+    fn with_events<R>(
+        &self,
+        markdown: &str,
+        prefix: Option<&str>,
+        f: impl FnOnce(BodyEvents<'_>) -> R,
+    ) -> R {
+        let markdown = match self.config.tab_width {
+            Some(width) => render::expand_leading_tabs(markdown, width),
+            None => Cow::Borrowed(markdown),
+        };
+        let mut events = render::merge_text(Parser::new_ext(&markdown, render::options()));
+        let stats = render::scan_body(&events);
+        // Before the transforms below, which add HTML of their own
+        match self.config.raw_html {
+            RawHtml::Allow => {}
+            RawHtml::Sanitize => events = render::sanitize_raw_html(events),
+            RawHtml::Escape => events = render::escape_raw_html(events),
+        }
+        if self.config.emoji_shortcodes {
+            events = render::replace_emoji_shortcodes(events);
+        }
+        if self.config.autolink {
+            events = render::autolink_urls(events);
+        }
+        if self.config.mention_url.is_some() || self.config.issue_url.is_some() {
+            events = render::link_references(
+                events,
+                self.config.mention_url.as_deref(),
+                self.config.issue_url.as_deref(),
+            );
+        }
+        if self.config.wrap_tables {
+            events = render::wrap_tables(events);
+        }
+        if let Some(prefix) = prefix {
+            events = render::prefix_anchor_links(events, prefix);
+        }
+        if let Some(ref template) = self.config.external_link_wrapper {
+            events = render::wrap_external_links(events, template);
+        }
+        // Without a custom renderer or line numbers, pulldown-cmark's own
+        // output is identical to `DefaultCodeBlockRenderer`
+        let renderer: Option<&dyn CodeBlockRenderer> = match self.code_renderer {
+            Some(ref renderer) => Some(renderer.as_ref()),
+            None if self.config.code_line_numbers => Some(&NumberedCodeBlockRenderer),
+            None => None,
+        };
+        if self.config.mermaid || renderer.is_some() {
+            events = render::transform_code_blocks(events, |lang, code| {
+                if self.config.mermaid && lang == Some("mermaid") {
+                    Some(render::mermaid_block(code))
+                } else {
+                    let theme = self.config.syntax_theme.as_deref();
+                    renderer.map(|renderer| renderer.render_with_theme(lang, code, theme))
+                }
+            });
+        }
+
+        // Add id attributes to headings to match Node.js marked behavior
+        let (id_fn, unique) = self.heading_id_fn();
+        let (events, headings, heading_starts) =
+            render::inject_heading_ids(events, prefix, id_fn, unique);
+        f(BodyEvents {
+            events,
+            headings,
+            heading_starts,
+            stats,
+        })
+    }
+
+    fn extract_categories(&self) -> Vec<String> {
+        let mut categories = std::collections::HashSet::new();
+
+        for paper in self.listed_papers() {
+            if let Some(ref tags) = paper.tags {
+                for tag in tags {
+                    categories.insert(tag.clone());
+                }
+            }
+        }
+
+        let mut sorted_categories: Vec<String> = categories.into_iter().collect();
+        sorted_categories.sort();
+        sorted_categories
+    }
+}
+
+/// A native API error as thrown to JavaScript
+fn to_js_error(error: ProcessError) -> JsValue {
+    JsValue::from_str(&error.to_string())
+}
+
+/// `text` cut to at most `max_chars` characters at a word boundary, ending
+/// with an ellipsis, or `None` if it already fits
+fn truncate_words(text: &str, max_chars: usize) -> Option<String> {
+    if text.chars().count() <= max_chars {
+        return None;
+    }
+
+    // Leave room for the ellipsis
+    let cut = text
+        .char_indices()
+        .nth(max_chars.saturating_sub(1))
+        .map_or(text.len(), |(idx, _)| idx);
+    let mut kept = &text[..cut];
+    if !text[cut..].starts_with(char::is_whitespace) {
+        if let Some(space) = kept.rfind(char::is_whitespace) {
+            kept = &kept[..space];
+        }
+    }
+    Some(format!("{}…", kept.trim_end()))
+}
+
+/// The list `source` picks from the manual, frontmatter and automatic ones
+fn select_toc(
+    source: &TocSource,
+    manual: &[String],
+    frontmatter: &[String],
+    auto: &[String],
+) -> Vec<String> {
+    match source {
+        TocSource::Manual => manual.to_vec(),
+        TocSource::Frontmatter => frontmatter.to_vec(),
+        TocSource::Auto => auto.to_vec(),
+        TocSource::Prefer(sources) => sources
+            .iter()
+            .map(|source| select_toc(source, manual, frontmatter, auto))
+            .find(|toc| !toc.is_empty())
+            .unwrap_or_default(),
+    }
+}
+
+/// `text` up to and including the first `.`, `!` or `?` followed by
+/// whitespace, or all of it
+fn first_sentence(text: &str) -> &str {
+    text.char_indices()
+        .find(|&(idx, c)| {
+            matches!(c, '.' | '!' | '?') && text[idx + 1..].starts_with(char::is_whitespace)
+        })
+        .map_or(text, |(idx, _)| &text[..idx + 1])
+}
+
+/// Minutes to read `word_count` words at 200 words a minute, rounded up
+fn reading_minutes(word_count: usize) -> usize {
+    word_count.div_ceil(200)
+}
+
+/// `name` trimmed, with runs of whitespace collapsed to one space
+fn normalize_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// What author names are compared by, so `Jane Doe` and `jane  doe` match
+fn author_key(name: &str) -> String {
+    normalize_name(name).to_lowercase()
+}
+
+/// Whether `word` is a generational suffix like `Jr.` that follows a surname
+fn is_name_suffix(word: &str) -> bool {
+    matches!(
+        word.trim_end_matches('.').to_lowercase().as_str(),
+        "jr" | "sr" | "ii" | "iii" | "iv"
+    )
+}
+
+/// Whether `line` opens or closes a fenced code block
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// Whether `text` starts like an ordered list item such as `1.` or `2)`
+fn is_ordered_item(text: &str) -> bool {
+    let digits = text.chars().take_while(|c| c.is_ascii_digit()).count();
+    digits > 0 && text[digits..].starts_with(['.', ')'])
+}
+
+/// The level of a setext heading whose text is `line` and underline is
+/// `next`: `===` is level 1 and `---` level 2. A `---` after a blank line,
+/// list item or other block marker is a thematic break, not an underline.
+fn setext_level(line: &str, next: &str) -> Option<u32> {
+    let text = line.trim();
+    if text.is_empty()
+        || line.starts_with("    ")
+        || text.starts_with(['#', '>', '-', '*', '+', '|', '<'])
+        || is_fence(line)
+        || is_ordered_item(text)
+    {
+        return None;
+    }
+
+    let underline = next.trim();
+    if next.starts_with("    ") || underline.is_empty() {
+        None
+    } else if underline.chars().all(|c| c == '=') {
+        Some(1)
+    } else if underline.chars().all(|c| c == '-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Normalize tags written as `#machine learning` or ` AI ` into
+/// `machine-learning` and `AI`, dropping empty and repeated tags
+fn normalize_tags(raw: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in raw {
+        let tag = tag.trim().trim_start_matches('#').trim();
+        let tag = tag.split_whitespace().collect::<Vec<_>>().join("-");
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// The light summary of a paper used by list endpoints
+fn list_item(paper: &Paper) -> serde_json::Value {
+    serde_json::json!({
+        "title": paper.title,
+        "slug": paper.slug,
+        "status": paper.status,
+        "tags": paper.tags.as_ref().unwrap_or(&vec![]),
+        "summary": paper.summary,
+        "metaDescription": paper.meta_description,
+        "lastUpdated": paper.last_updated,
+        "authors": paper.authors,
+        "hasMath": paper.has_math,
+        "hasCode": paper.has_code,
+        "tasksDone": paper.tasks_done,
+        "tasksTotal": paper.tasks_total,
+        "wordCount": paper.word_count,
+        "readingMinutes": paper.reading_minutes,
+        "isStub": paper.is_stub,
+        "coverImage": paper.cover_image,
+        "redirectUrl": paper.redirect_url,
+        "layout": paper.layout,
+        "classes": paper.classes
+    })
+}
+
+/// Page `page` (from 1) of `items`, `per_page` at a time, with totals. Pages
+/// past the end have no items. Without `per_page` everything is one page.
+fn paginate<T: Serialize>(
+    items: Vec<T>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+) -> serde_json::Value {
+    let total = items.len();
+    let page = page.unwrap_or(1).max(1);
+    let per_page = per_page.unwrap_or(total).max(1);
+    let total_pages = total.div_ceil(per_page);
+    let items: Vec<T> = items
+        .into_iter()
+        .skip((page - 1).saturating_mul(per_page))
+        .take(per_page)
+        .collect();
+
+    serde_json::json!({
+        "items": items,
+        "page": page,
+        "total": total,
+        "total_pages": total_pages
+    })
+}
+
+/// The filename without its `.md` extension. Non-ASCII names are
+/// transliterated (`論文.md` becomes `lun-wen`) and fall back to
+/// `paper-{hash}` if nothing usable is left.
+fn slug_from_filename(filename: &str) -> String {
+    let stem = filename.strip_suffix(".md").unwrap_or(filename);
+    if stem.is_ascii() {
+        return stem.to_string();
+    }
+
+    let slug = slugify(&deunicode::deunicode(stem));
+    if slug.is_empty() {
+        format!("paper-{:08x}", fnv1a_64(stem.as_bytes()) as u32)
+    } else {
+        slug
+    }
+}
+
+/// Whether `text` can appear in a URL path segment without escaping
+fn is_url_safe(text: &str) -> bool {
+    text.chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '~'))
+}
+
+/// Lowercase `text` and replace every run of other characters than letters
+/// and digits with a single `-`
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// A hue in `0..360` for `tag`: the 32-bit FNV-1a hash of its UTF-8 bytes,
+/// modulo 360. It depends only on the tag, so it is stable across corpora.
+pub fn tag_hue(tag: &str) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in tag.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash % 360
+}
+
+/// The 64-bit FNV-1a hash of `bytes`
+fn fnv1a_64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Hash of a source file, separating the filename from the content so the
+/// boundary between them matters
+fn content_hash(filename: &str, content: &str) -> u64 {
+    fnv1a_64(format!("{}\0{}", filename, content).as_bytes())
+}
+
+/// Current time as an ISO 8601 string, like `Date.prototype.toISOString`
+fn now_iso() -> String {
+    dates::format_iso_millis(now_unix_millis())
+}
+
+/// Milliseconds since the Unix epoch
+#[cfg(target_arch = "wasm32")]
+fn now_unix_millis() -> i64 {
+    js_sys::Date::now() as i64
+}
+
+/// Milliseconds since the Unix epoch
+#[cfg(not(target_arch = "wasm32"))]
+fn now_unix_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Current high-resolution time in microseconds
+#[cfg(target_arch = "wasm32")]
+fn now_micros() -> f64 {
+    let millis = web_sys::window()
+        .and_then(|window| window.performance())
+        .map(|performance| performance.now())
+        .unwrap_or_else(js_sys::Date::now);
+    millis * 1000.0
+}
+
+/// Current high-resolution time in microseconds
+#[cfg(not(target_arch = "wasm32"))]
+fn now_micros() -> f64 {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START
+        .get_or_init(std::time::Instant::now)
+        .elapsed()
+        .as_secs_f64()
+        * 1_000_000.0
+}
+
+/// Log a processing warning and record it on the paper
+fn warn(warnings: &mut Vec<String>, message: String) {
+    console_log!("WARNING: {}", message);
+    warnings.push(message);
+}
+
+/// Utility function to get the heading outline of a markdown body as JSON
+/// `[{level, text, id}]`, with the ids papers use
+#[wasm_bindgen]
+pub fn extract_headings_json(markdown: &str) -> Result<String, JsValue> {
+    json::to_string(&extract_headings(markdown), true)
+        .map_err(|e| JsValue::from_str(&format!("Failed to serialize headings: {}", e)))
+}
+
+/// Every heading in a markdown body, with the ids papers use
+pub fn extract_headings(markdown: &str) -> Vec<Heading> {
+    let events = render::merge_text(Parser::new_ext(markdown, render::options()));
+    render::inject_heading_ids(events, None, Some(&render::heading_id), false).1
+}
+
+/// Utility function to strip a markdown body down to plain prose, dropping
+/// markup and code blocks and collapsing whitespace
+#[wasm_bindgen]
+pub fn markdown_to_text(markdown: &str) -> String {
+    let events = render::merge_text(Parser::new_ext(markdown, render::options()));
+    render::plain_text(&events)
+}
+
+/// Utility function to render a markdown body to HTML exactly as a paper's
+/// `html` is rendered with the default configuration, for live previews.
+/// Frontmatter is not parsed.
+#[wasm_bindgen]
+pub fn render_markdown(markdown: &str) -> String {
+    PaperProcessor::new().markdown_to_html(markdown, None).html
+}
+
+/// Utility function to extract the `.md` files from a tar archive, failing
+/// without reading it when it is larger than `max_bytes`, if given
+#[wasm_bindgen]
+pub fn process_tar_archive(
+    tar_data: &[u8],
+    max_bytes: Option<usize>,
+) -> Result<js_sys::Array, JsValue> {
+    process_tar_archive_with_extensions(tar_data, vec![".md".to_string()], max_bytes)
+}
+
+/// Extract files from a tar archive whose names end with one of `extensions`,
+/// such as `[".md", ".markdown", ".mdx"]`, failing without reading it when it
+/// is larger than `max_bytes`, if given
+#[wasm_bindgen]
+pub fn process_tar_archive_with_extensions(
+    tar_data: &[u8],
+    extensions: Vec<String>,
+    max_bytes: Option<usize>,
+) -> Result<js_sys::Array, JsValue> {
+    let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+    let files = read_tar_archive_limited(tar_data, &extensions, max_bytes)
+        .map_err(|e| JsValue::from_str(&format!("Failed to read archive: {}", e)))?;
+    source_files_array(files)
+}
+
+/// `files` as an array of `{filename, content, mtime?}` objects
+fn source_files_array(files: Vec<SourceFile>) -> Result<js_sys::Array, JsValue> {
+    let array = js_sys::Array::new();
+    for file in files {
+        let file_obj = js_sys::Object::new();
+        js_sys::Reflect::set(&file_obj, &"filename".into(), &file.filename.into())?;
+        js_sys::Reflect::set(&file_obj, &"content".into(), &file.content.into())?;
+        if let Some(mtime) = file.mtime {
+            js_sys::Reflect::set(&file_obj, &"mtime".into(), &mtime.into())?;
+        }
+        array.push(&file_obj);
+    }
+    Ok(array)
+}
+
+/// A numeric tar header field: octal digits ended by a NUL or space, or, when
+/// the high bit of the first byte is set, a big-endian base-256 number as GNU
+/// tar writes for values too large for octal
+fn tar_number(field: &[u8]) -> Option<u64> {
+    match field.split_first() {
+        Some((&first, rest)) if first & 0x80 != 0 => {
+            // Negative numbers start with all bits set
+            if first & 0x40 != 0 {
+                return None;
+            }
+            rest.iter()
+                .try_fold(u64::from(first & 0x3f), |value, &byte| {
+                    value.checked_mul(256).map(|value| value | u64::from(byte))
+                })
+        }
+        _ => {
+            let field = &field[field.iter().take_while(|&&b| b == b' ').count()..];
+            let end = field
+                .iter()
+                .position(|&b| b == 0 || b == b' ')
+                .unwrap_or(field.len());
+            u64::from_str_radix(std::str::from_utf8(&field[..end]).ok()?, 8).ok()
+        }
+    }
+}
+
+/// `read_tar_archive`, unless the archive is larger than `max_bytes`
+pub fn read_tar_archive_limited(
+    tar_data: &[u8],
+    extensions: &[&str],
+    max_bytes: Option<usize>,
+) -> Result<Vec<SourceFile>, ProcessError> {
+    if let Some(limit) = max_bytes {
+        if tar_data.len() > limit {
+            return Err(ProcessError::TooLarge {
+                size: tar_data.len(),
+                limit,
+            });
+        }
+    }
+    Ok(read_tar_archive(tar_data, extensions))
+}
+
+/// Read the files in a tar archive whose names end with one of `extensions`
+pub fn read_tar_archive(tar_data: &[u8], extensions: &[&str]) -> Vec<SourceFile> {
+    let mut files = Vec::new();
+
+    // Simple tar parsing - this is a basic implementation
+    // For production, you might want to use a proper tar library
+    let mut offset = 0;
+
+    while offset + 512 <= tar_data.len() {
+        let header = &tar_data[offset..offset + 512];
+
+        // Check if this is the end of the archive (all zeros)
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+
+        // Parse filename (first 100 bytes, null-terminated)
+        let filename_bytes = &header[0..100];
+        let filename_end = filename_bytes.iter().position(|&b| b == 0).unwrap_or(100);
+        let filename = String::from_utf8_lossy(&filename_bytes[..filename_end]).to_string();
+
+        // Parse file size (12 bytes starting at offset 124)
+        let size = tar_number(&header[124..136]).unwrap_or(0);
+
+        // Parse modification time (12 bytes starting at offset 136, seconds)
+        let mtime = tar_number(&header[136..148]).map(|seconds| seconds as f64 * 1000.0);
+
+        offset += 512; // Move past header
+
+        // A size running past the end of the data means the archive is
+        // truncated or the header is corrupt, so nothing after it can be read
+        let content_end = usize::try_from(size)
+            .ok()
+            .and_then(|size| offset.checked_add(size))
+            .filter(|&end| end <= tar_data.len());
+        let Some(content_end) = content_end else {
+            break;
+        };
+
+        if size > 0 && extensions.iter().any(|ext| filename.ends_with(ext)) {
+            let content = &tar_data[offset..content_end];
+            let content = String::from_utf8_lossy(content).to_string();
+            files.push(SourceFile {
+                filename,
+                content,
+                mtime,
+            });
+        }
+
+        // Skip to the next header, rounding up to a 512-byte boundary
+        let next = size
+            .div_ceil(512)
+            .checked_mul(512)
+            .and_then(|padded| usize::try_from(padded).ok())
+            .and_then(|padded| offset.checked_add(padded));
+        let Some(next) = next else {
+            break;
+        };
+        offset = next;
+    }
+
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(processor: &mut PaperProcessor, filename: &str, content: &str) -> Paper {
+        processor.process(filename, content).unwrap().clone()
+    }
+
+    #[test]
+    fn heading_id_prefix_applies_to_in_page_links() {
+        let mut processor = PaperProcessor::new();
+        processor.set_heading_id_prefix(Some("{slug}".to_string()));
+        let paper = process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: B\n---\n## Methodology\n\n[see](#methodology)[^1]\n\n[^1]: Note\n",
+        );
+        assert!(paper.html.contains("<h2 id=\"b__methodology\">"));
+        assert!(paper.html.contains("href=\"#b__methodology\""));
+        assert!(paper.html.contains("href=\"#b__1\""));
+        assert!(paper.html.contains("id=\"b__1\""));
+        assert_eq!(paper.headings[0].id, "b__methodology");
+    }
+
+    #[test]
+    fn anchor_links_are_checked_against_heading_ids() {
+        let content = "---\ntitle: A\n---\n## Methodology\n\n[ok](#methodology) [bad](#methods)\n";
+        let paper = process(&mut PaperProcessor::new(), "a.md", content);
+        assert_eq!(
+            paper.warnings,
+            ["Link to #methods does not match any heading"]
+        );
+    }
+
+    #[test]
+    fn anchor_check_allows_for_heading_id_prefix() {
+        let mut processor = PaperProcessor::new();
+        processor.set_heading_id_prefix(Some("{slug}".to_string()));
+        processor.set_strict(true);
+        let paper = process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\n---\n## Methodology\n\n[see](#methodology)\n",
+        );
+        assert!(paper.warnings.is_empty(), "{:?}", paper.warnings);
+        assert!(processor.broken_links().is_empty());
+    }
+
+    /// A tar header for `name` with `size` as its raw size field
+    fn tar_header(name: &str, size: [u8; 12]) -> Vec<u8> {
+        let mut header = vec![0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..136].copy_from_slice(&size);
+        header[136..148].copy_from_slice(b"00000000012\0");
+        header
+    }
+
+    fn octal_size(size: usize) -> [u8; 12] {
+        let mut field = [0u8; 12];
+        field[..11].copy_from_slice(format!("{:011o}", size).as_bytes());
+        field
+    }
+
+    fn base256_size(size: u64) -> [u8; 12] {
+        let mut field = [0u8; 12];
+        field[4..].copy_from_slice(&size.to_be_bytes());
+        field[0] = 0x80;
+        field
+    }
+
+    fn tar_entry(name: &str, size: [u8; 12], content: &[u8]) -> Vec<u8> {
+        let mut entry = tar_header(name, size);
+        entry.extend_from_slice(content);
+        entry.resize(512 + content.len().div_ceil(512) * 512, 0);
+        entry
+    }
+
+    #[test]
+    fn tar_number_reads_octal_and_base256() {
+        assert_eq!(tar_number(b"00000000017\0"), Some(15));
+        assert_eq!(tar_number(b"17 "), Some(15));
+        assert_eq!(tar_number(&base256_size(1 << 40)), Some(1 << 40));
+        assert_eq!(tar_number(&[0xff; 12]), None);
+    }
+
+    #[test]
+    fn read_tar_archive_reads_octal_and_base256_sizes() {
+        let mut tar = tar_entry("a.md", octal_size(5), b"hello");
+        tar.extend(tar_entry("b.md", base256_size(5), b"world"));
+        tar.extend(tar_entry("c.txt", octal_size(3), b"txt"));
+        tar.extend([0u8; 1024]);
+
+        let files = read_tar_archive(&tar, &[".md"]);
+        let names: Vec<_> = files.iter().map(|f| f.filename.as_str()).collect();
+        assert_eq!(names, ["a.md", "b.md"]);
+        assert_eq!(files[1].content, "world");
+        assert_eq!(files[0].mtime, Some(10_000.0));
+    }
+
+    #[test]
+    fn read_tar_archive_stops_at_huge_sizes() {
+        let mut tar = tar_entry("a.md", octal_size(5), b"hello");
+        tar.extend(tar_entry("b.md", base256_size(u64::MAX >> 1), b"x"));
+        tar.extend(tar_entry("c.md", octal_size(1), b"y"));
+
+        let files = read_tar_archive(&tar, &[".md"]);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].filename, "a.md");
+    }
+
+    #[test]
+    fn read_tar_archive_stops_when_truncated() {
+        let mut tar = tar_entry("a.md", octal_size(5), b"hello");
+        tar.extend(tar_header("b.md", octal_size(4096)));
+        tar.extend([b'x'; 100]);
+
+        let files = read_tar_archive(&tar, &[".md"]);
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn read_tar_archive_limited_rejects_large_archives() {
+        let tar = tar_entry("a.md", octal_size(5), b"hello");
+        assert!(matches!(
+            read_tar_archive_limited(&tar, &[".md"], Some(512)),
+            Err(ProcessError::TooLarge {
+                size: 1024,
+                limit: 512
+            })
+        ));
+        let files = read_tar_archive_limited(&tar, &[".md"], Some(1024)).unwrap();
+        assert_eq!(files[0].content, "hello");
+        assert_eq!(
+            read_tar_archive_limited(&tar, &[".md"], None)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn section_html_by_id_ends_at_next_heading_of_same_level() {
+        let mut processor = PaperProcessor::new();
+        let content = "---\ntitle: A\n---\n## One\n\nfirst\n\n## Two\n\nsecond\n\n### Sub\n\nnested\n\n## Three\n\nthird\n";
+        process(&mut processor, "a.md", content);
+        let html = processor.section_html_by_id("a", "two").unwrap();
+        assert!(html.starts_with("<h2 id=\"two\">Two</h2>"));
+        assert!(html.contains("nested"));
+        assert!(!html.contains("first") && !html.contains("third"));
+        assert_eq!(processor.section_html_by_id("a", "missing"), None);
+    }
+
+    #[test]
+    fn section_html_by_id_keeps_numbered_github_ids() {
+        let mut processor = PaperProcessor::new();
+        processor.set_heading_id_style("github").unwrap();
+        let content = "---\ntitle: A\n---\n## Intro\n\nfirst\n\n## Intro\n\nsecond\n";
+        process(&mut processor, "a.md", content);
+        let html = processor.section_html_by_id("a", "intro-1").unwrap();
+        assert!(html.contains("<h2 id=\"intro-1\">"));
+        assert!(html.contains("second") && !html.contains("first"));
+    }
+
+    #[test]
+    fn section_html_by_id_finds_headings_with_emoji_shortcodes() {
+        let mut processor = PaperProcessor::new();
+        processor.set_emoji_shortcodes(true);
+        let content = "---\ntitle: A\n---\n## :tada: Launch\n\nshipped\n";
+        let paper = process(&mut processor, "a.md", content);
+        let html = processor
+            .section_html_by_id("a", &paper.headings[0].id)
+            .unwrap();
+        assert!(html.contains("🎉 Launch") && html.contains("shipped"));
+    }
+
+    #[test]
+    fn section_html_by_id_leaves_out_stripped_title_heading() {
+        let mut processor = PaperProcessor::new();
+        processor.set_strip_title_heading(true);
+        let content =
+            "---\ntitle: Paper\n---\n# Paper\n\nlead\n\n## Body\n\ntext\n\n## End\n\nlast\n";
+        process(&mut processor, "a.md", content);
+        assert_eq!(processor.section_html_by_id("a", "paper"), None);
+        let html = processor.section_html_by_id("a", "body").unwrap();
+        assert!(html.starts_with("<h2 id=\"body\">"));
+        assert!(!html.contains("lead") && !html.contains("last"));
+    }
+
+    #[test]
+    fn native_setters_reject_unknown_values() {
+        let mut processor = PaperProcessor::new();
+        let error = processor.set_raw_html("strip").unwrap_err();
+        assert_eq!(error.to_string(), "Unknown raw HTML mode: strip");
+        assert!(processor.set_section_level(7).is_err());
+        assert!(processor.set_toc_source("auto").is_ok());
+        assert_eq!(
+            processor.config().toc_source,
+            TocSource::parse("auto").unwrap()
+        );
+    }
+
+    #[test]
+    fn with_config_and_config_mut_configure_processing() {
+        let config = ProcessorConfig {
+            heading_id_style: HeadingIdStyle::None,
+            ..ProcessorConfig::default()
+        };
+        let mut processor = PaperProcessor::with_config(config);
+        let paper = process(&mut processor, "a.md", "---\ntitle: A\n---\n## One\n");
+        assert!(paper.html.contains("<h2>One</h2>"));
+
+        processor.config_mut().heading_id_style = HeadingIdStyle::Kebab;
+        let paper = process(&mut processor, "b.md", "---\ntitle: B\n---\n## One\n");
+        assert!(paper.html.contains("<h2 id=\"one\">One</h2>"));
+    }
+
+    #[test]
+    fn search_stemming_matches_models_to_model() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\n---\nWe train one model.\n",
+        );
+        assert!(processor.search_results("models").is_empty());
+
+        processor.set_search_stemming(true);
+        let hits = processor.search_results("models");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].slug, "a");
+    }
+
+    #[test]
+    fn atom_feed_is_well_formed_xml() {
+        let mut processor = PaperProcessor::new();
+        let content = "---\ntitle: 'Q&A <b> \"quoted\" ''single'''\nauthors: ['Tom & Jerry', 'O''Brien <x>']\ntags: ['r&d']\nlastUpdated: 2024-01-02\n---\nBody & more\n";
+        process(&mut processor, "qa.md", content);
+        let xml = processor.generate_atom("https://example.com/", "Papers & \"Notes\"");
+
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let feed = doc.root_element();
+        assert_eq!(feed.tag_name().name(), "feed");
+        let child = |node: roxmltree::Node<'_, '_>, name: &str| {
+            node.children()
+                .find(|n| n.has_tag_name(name))
+                .and_then(|n| n.text().map(str::to_string))
+        };
+        assert_eq!(child(feed, "id").as_deref(), Some("https://example.com/"));
+        assert!(child(feed, "updated").is_some());
+
+        let entry = feed.children().find(|n| n.has_tag_name("entry")).unwrap();
+        assert_eq!(
+            child(entry, "title").as_deref(),
+            Some("Q&A <b> \"quoted\" 'single'")
+        );
+        let authors: Vec<String> = entry
+            .children()
+            .filter(|n| n.has_tag_name("author"))
+            .filter_map(|n| child(n, "name"))
+            .collect();
+        assert_eq!(authors, ["Tom & Jerry", "O'Brien <x>"]);
+        let category = entry
+            .children()
+            .find(|n| n.has_tag_name("category"))
+            .unwrap();
+        assert_eq!(category.attribute("term"), Some("r&d"));
+    }
+
+    #[test]
+    fn yaml_errors_report_the_file_line() {
+        let mut processor = PaperProcessor::new();
+        processor.set_strict(true);
+        let content = "---\ntitle: A\nnote: a: b\n---\nBody\n";
+        match processor.process("a.md", content) {
+            Err(error @ ProcessError::Yaml { line, .. }) => {
+                assert_eq!(line, Some(3));
+                assert!(error.to_string().contains("line 3"), "{}", error);
+            }
+            other => panic!("expected a YAML error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn broken_links_are_found_and_fail_the_strict_check() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\n---\n## Results\n\nSee [[b]] and [[b#method]].\n",
+        );
+        process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: B\n---\n## Method\n\n[[a#results]] [[a#missing]] [[gone]] [x](#nowhere)\n",
+        );
+
+        let broken = processor.broken_links();
+        let links: Vec<(&str, &str)> = broken
+            .iter()
+            .map(|link| (link.slug.as_str(), link.link.as_str()))
+            .collect();
+        assert_eq!(
+            links,
+            [("b", "[[a#missing]]"), ("b", "[[gone]]"), ("b", "#nowhere")]
+        );
+
+        let error = processor.assert_no_broken_links().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Found 3 broken link(s):\n  b: [[a#missing]]\n  b: [[gone]]\n  b: #nowhere"
+        );
+    }
+
+    #[test]
+    fn clean_corpus_passes_the_broken_link_check() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\n---\n## Top\n\n[[a#top]]\n",
+        );
+        assert!(processor.broken_links().is_empty());
+        assert!(processor.assert_no_broken_links().is_ok());
+    }
+
+    #[test]
+    fn setext_headings_give_sections_toc_and_ids() {
+        let content = "---\ntitle: A\n---\nPaper\n=====\n\nTable of Contents\n-----------------\n1. **Intro**\n2. **Method**\n\nIntro\n-----\nfirst\n\n---\n\nMethod\n------\nsecond\n";
+        let paper = process(&mut PaperProcessor::new(), "a.md", content);
+        assert_eq!(paper.toc, ["Intro", "Method"]);
+        assert_eq!(paper.sections["intro"], "first\n\n---");
+        assert_eq!(paper.sections["method"], "second");
+        let headings: Vec<(u32, &str)> = paper
+            .headings
+            .iter()
+            .map(|heading| (heading.level, heading.id.as_str()))
+            .collect();
+        assert_eq!(
+            headings,
+            [
+                (1, "paper"),
+                (2, "table-of-contents"),
+                (2, "intro"),
+                (2, "method")
+            ]
+        );
+    }
+
+    fn date_processor(formats: &str) -> PaperProcessor {
+        let mut processor = PaperProcessor::new();
+        processor.set_date_keys(vec!["lastUpdated".into(), "updated".into(), "date".into()]);
+        processor.set_date_formats(formats).unwrap();
+        processor
+    }
+
+    #[test]
+    fn date_is_read_from_an_alias_key() {
+        let mut processor = date_processor("iso");
+        let paper = process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\ndate: 2024-03-05\n---\n",
+        );
+        assert_eq!(paper.last_updated, "2024-03-05");
+        assert_eq!(paper.last_updated_source.unwrap().key, "date");
+    }
+
+    #[test]
+    fn unreadable_date_keys_fall_through_to_the_next() {
+        let mut processor = date_processor("iso");
+        let content = "---\ntitle: A\nlastUpdated: soon\ndate: 2024-03-05\n---\n";
+        let paper = process(&mut processor, "a.md", content);
+        assert_eq!(paper.last_updated, "2024-03-05");
+        assert!(paper.warnings.is_empty(), "{:?}", paper.warnings);
+
+        processor.set_strict(true);
+        assert!(processor.process("b.md", content).is_ok());
+        let content = "---\ntitle: C\nlastUpdated: soon\ndate: later\n---\n";
+        assert!(matches!(
+            processor.process("c.md", content),
+            Err(ProcessError::InvalidDate(value)) if value == "soon"
+        ));
+    }
+
+    #[test]
+    fn numeric_dates_follow_the_format_order() {
+        let content = "---\ntitle: A\nupdated: 03/04/2024\n---\n";
+        let paper = process(&mut date_processor("dmy,mdy"), "a.md", content);
+        assert_eq!(paper.last_updated, "2024-04-03");
+        let paper = process(&mut date_processor("mdy,dmy"), "a.md", content);
+        assert_eq!(paper.last_updated, "2024-03-04");
+
+        // Only valid day-first, so read that way whatever the order
+        let content = "---\ntitle: A\nupdated: 31/12/2024\n---\n";
+        let paper = process(&mut date_processor("mdy,dmy"), "a.md", content);
+        assert_eq!(paper.last_updated, "2024-12-31");
+        assert_eq!(paper.last_updated_source.unwrap().format, DateFormat::Dmy);
+    }
+
+    struct ThemeNameRenderer;
+
+    impl CodeBlockRenderer for ThemeNameRenderer {
+        fn render(&self, _: Option<&str>, _: &str) -> String {
+            "<pre>no theme</pre>".to_string()
+        }
+
+        fn render_with_theme(&self, _: Option<&str>, _: &str, theme: Option<&str>) -> String {
+            format!("<pre>{}</pre>", theme.unwrap_or("no theme"))
+        }
+    }
+
+    #[test]
+    fn syntax_theme_must_be_a_known_theme() {
+        let mut processor = PaperProcessor::new();
+        let error = processor.set_syntax_theme(Some("Dracula")).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Unknown syntax theme: Dracula"));
+        assert_eq!(processor.config().syntax_theme, None);
+    }
+
+    #[test]
+    fn syntax_theme_reaches_the_code_block_renderer() {
+        let mut processor = PaperProcessor::new();
+        processor.set_code_block_renderer(ThemeNameRenderer);
+        processor
+            .set_syntax_theme(Some("base16-ocean.dark"))
+            .unwrap();
+        let paper = process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\n---\n```rust\nfn main() {}\n```\n",
+        );
+        assert!(paper.html.contains("<pre>base16-ocean.dark</pre>"));
+    }
+
+    #[test]
+    fn rerender_all_recomputes_render_warnings() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\nsummary: S\n---\n## Methodology\n\n[see](#methodology)\n",
+        );
+        let missing_title = "Paper a.md is missing a title in frontmatter!";
+        assert_eq!(processor.papers[0].warnings, [missing_title]);
+
+        processor.set_heading_id_style("none").unwrap();
+        processor.set_stub_word_threshold(100);
+        processor.rerender_all();
+        let paper = &processor.papers[0];
+        assert!(paper.is_stub);
+        assert_eq!(
+            paper.warnings,
+            [
+                missing_title,
+                "Link to #methodology does not match any heading",
+                "Paper has 2 words, fewer than the stub threshold of 100",
+            ]
+        );
+
+        processor.set_heading_id_style("kebab").unwrap();
+        processor.set_stub_word_threshold(0);
+        processor.rerender_all();
+        let paper = &processor.papers[0];
+        assert!(!paper.is_stub);
+        assert_eq!(paper.warnings, [missing_title]);
+        assert!(paper.render_warnings.is_empty());
+    }
+
+    #[test]
+    fn title_falls_back_to_first_h1_then_slug() {
+        let content = "---\nsummary: S\n---\n## Aside\n\n# Intro to ML\n";
+        let paper = process(&mut PaperProcessor::new(), "intro-to-ml.md", content);
+        assert_eq!(paper.title, "Intro to ML");
+        assert!(paper.warnings.is_empty(), "{:?}", paper.warnings);
+
+        let mut processor = PaperProcessor::new();
+        processor.set_title_from_heading(false);
+        let paper = process(&mut processor, "intro-to-ml.md", content);
+        assert_eq!(paper.title, "intro-to-ml");
+        assert_eq!(paper.errors.len(), 1);
+    }
+
+    #[test]
+    fn strict_mode_accepts_a_title_from_the_first_h1() {
+        let mut processor = PaperProcessor::new();
+        processor.set_strict(true);
+        let paper = processor
+            .process("a.md", "---\nsummary: S\n---\n# Heading Title\n")
+            .unwrap();
+        assert_eq!(paper.title, "Heading Title");
+        assert!(matches!(
+            processor.process("b.md", "---\nsummary: S\n---\nNo heading\n"),
+            Err(ProcessError::MissingTitle(_))
+        ));
+    }
+
+    #[test]
+    fn fuzzy_search_tolerates_typos() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: Deep learning\n---\nText\n",
+        );
+        process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: Yearning towers\n---\nText\n",
+        );
+
+        assert!(processor.fuzzy_search_results("learing", 0).is_empty());
+        let hits = processor.fuzzy_search_results("learing", 2);
+        let slugs: Vec<&str> = hits.iter().map(|hit| hit.slug.as_str()).collect();
+        assert_eq!(slugs, ["a", "b"]);
+        assert!(hits[0].score > hits[1].score);
+    }
+
+    #[test]
+    fn duplicate_frontmatter_keys_are_reported() {
+        let content = "---\ntitle: First\nsummary: S\ntitle: Second\n---\nBody\n";
+        let paper = process(&mut PaperProcessor::new(), "a.md", content);
+        assert_eq!(paper.warnings, ["Duplicate frontmatter key `title`"]);
+        assert_eq!(paper.errors, paper.warnings);
+
+        let mut processor = PaperProcessor::new();
+        processor.set_strict(true);
+        assert!(matches!(
+            processor.process("a.md", content),
+            Err(ProcessError::DuplicateKey(key)) if key == "title"
+        ));
+    }
+
+    #[test]
+    fn lenient_mode_keeps_papers_without_a_title() {
+        let paper = process(
+            &mut PaperProcessor::new(),
+            "untitled.md",
+            "---\nsummary: S\n---\nBody\n",
+        );
+        assert_eq!(paper.title, "untitled");
+        assert_eq!(
+            paper.errors,
+            ["Paper untitled.md is missing a title in frontmatter!"]
+        );
+    }
+
+    #[test]
+    fn strict_mode_fails_on_problems() {
+        let mut processor = PaperProcessor::new();
+        processor.set_strict(true);
+        let results = [
+            processor
+                .process("a.md", "---\nsummary: S\n---\nBody\n")
+                .map(|_| ()),
+            processor.process("b.md", "Body only\n").map(|_| ()),
+            processor
+                .process("c.md", "---\ntitle: C\nlastUpdated: someday\n---\n")
+                .map(|_| ()),
+            processor
+                .process("d.md", "---\ntitle: D\nauthors: 7\n---\n")
+                .map(|_| ()),
+        ];
+        assert!(matches!(results[0], Err(ProcessError::MissingTitle(_))));
+        assert!(matches!(results[1], Err(ProcessError::MissingFrontmatter)));
+        assert!(matches!(results[2], Err(ProcessError::InvalidDate(_))));
+        assert!(matches!(results[3], Err(ProcessError::MalformedAuthors(_))));
+        assert_eq!(processor.papers.len(), 0);
+    }
+
+    #[test]
+    fn messy_tags_are_normalized() {
+        let content = "---\ntitle: A\ntags: ['#machine learning', ' AI ', '', '#', 'AI']\n---\n";
+        let paper = process(&mut PaperProcessor::new(), "a.md", content);
+        assert_eq!(paper.tags.unwrap(), ["machine-learning", "AI"]);
+        assert_eq!(
+            paper.tags_raw.unwrap(),
+            ["#machine learning", " AI ", "", "#", "AI"]
+        );
+
+        let paper = process(
+            &mut PaperProcessor::new(),
+            "b.md",
+            "---\ntitle: B\ntags: [ok]\n---\n",
+        );
+        assert_eq!(paper.tags_raw, None);
+    }
+
+    #[test]
+    fn missing_summaries_use_the_paper_language_default() {
+        let mut processor = PaperProcessor::new();
+        processor.set_default_summary("es", "Sin resumen");
+        let paper = process(&mut processor, "a.md", "---\ntitle: A\nlang: es\n---\n");
+        assert_eq!(paper.summary, "Sin resumen");
+        let paper = process(&mut processor, "b.md", "---\ntitle: B\nlang: fr\n---\n");
+        assert_eq!(paper.summary, "No summary available");
+    }
+
+    #[test]
+    fn task_list_progress_counts_nested_items() {
+        let content = "---\ntitle: A\n---\n- [x] One\n- [ ] Two\n  - [x] Two a\n  - [ ] Two b\n- Plain item\n";
+        let paper = process(&mut PaperProcessor::new(), "a.md", content);
+        assert_eq!((paper.tasks_done, paper.tasks_total), (2, 4));
+    }
+
+    #[test]
+    fn cover_image_comes_from_frontmatter_or_the_first_image() {
+        let body = "![One](img/one.png)\n\n![Two](img/two.png)\n";
+        let mut processor = PaperProcessor::new();
+        let paper = process(
+            &mut processor,
+            "a.md",
+            &format!("---\ntitle: A\ncover: c.jpg\n---\n{}", body),
+        );
+        assert_eq!(paper.cover_image.as_deref(), Some("c.jpg"));
+        let paper = process(
+            &mut processor,
+            "b.md",
+            &format!("---\ntitle: B\n---\n{}", body),
+        );
+        assert_eq!(paper.cover_image.as_deref(), Some("img/one.png"));
+        let paper = process(&mut processor, "c.md", "---\ntitle: C\n---\nNo images\n");
+        assert_eq!(paper.cover_image, None);
+
+        let list: serde_json::Value =
+            serde_json::from_str(&processor.get_papers_list_json().unwrap()).unwrap();
+        assert_eq!(list[1]["coverImage"], "img/one.png");
+    }
+
+    #[test]
+    fn excluded_sections_are_left_out_case_insensitively() {
+        let mut processor = PaperProcessor::new();
+        processor.set_excluded_sections(vec!["References".to_string()]);
+        let content = "---\ntitle: A\n---\n## Method\n\nSteps\n\n## REFERENCES\n\n1. Someone\n";
+        let paper = process(&mut processor, "a.md", content);
+        assert_eq!(paper.section_names, ["method"]);
+        assert!(!paper.sections.contains_key("references"));
+    }
+
+    #[test]
+    fn read_tar_archive_filters_by_extension() {
+        let mut tar = tar_entry("a.md", octal_size(1), b"a");
+        tar.extend(tar_entry("b.markdown", octal_size(1), b"b"));
+        tar.extend(tar_entry("c.txt", octal_size(1), b"c"));
+
+        let names = |extensions: &[&str]| -> Vec<String> {
+            read_tar_archive(&tar, extensions)
+                .into_iter()
+                .map(|file| file.filename)
+                .collect()
+        };
+        assert_eq!(names(&[".md"]), ["a.md"]);
+        assert_eq!(names(&[".markdown", ".mdx"]), ["b.markdown"]);
+    }
+
+    #[test]
+    fn malformed_yaml_keeps_the_body() {
+        let content = "---\ntitle: [unclosed\n---\n## Method\n\nSteps\n";
+        let paper = process(&mut PaperProcessor::new(), "broken.md", content);
+        assert_eq!(paper.title, "broken");
+        assert!(paper.had_frontmatter);
+        assert!(paper.html.contains("<h2 id=\"method\">Method</h2>"));
+        assert!(paper.warnings[0].starts_with("Invalid frontmatter YAML"));
+    }
+
+    #[test]
+    fn includes_are_resolved_and_cycles_reported() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\n---\n## License\n\nCC BY 4.0\n",
+        );
+        process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: B\n---\n{{include: a#license}}\n",
+        );
+        process(
+            &mut processor,
+            "c.md",
+            "---\ntitle: C\n---\n## Loop\n\n{{include: c#loop}}\n",
+        );
+        processor.resolve_includes();
+
+        let b = processor.paper("b").unwrap();
+        assert!(b.html.contains("<p>CC BY 4.0</p>"));
+        assert!(b.warnings.is_empty());
+        let c = processor.paper("c").unwrap();
+        assert_eq!(c.warnings, ["Include cycle: c#loop -> c#loop"]);
+    }
+
+    #[test]
+    fn section_offsets_cover_each_section() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\n---\nIntro\n## One\nfirst\n## Two\nsecond\n",
+        );
+        let sections = processor.section_offsets("a").unwrap();
+        let content = &processor.paper("a").unwrap().content;
+        let spans: Vec<(&str, usize, usize, &str)> = sections
+            .iter()
+            .map(|s| (s.name.as_str(), s.start, s.end, &content[s.start..s.end]))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                ("one", 6, 19, "## One\nfirst\n"),
+                ("two", 19, 33, "## Two\nsecond\n"),
+            ]
+        );
+        assert_eq!(sections[1].text, "second");
+    }
+
+    #[test]
+    fn last_updated_fallback_modes() {
+        let content = "---\ntitle: A\n---\n";
+        let mut processor = PaperProcessor::new();
+        processor.set_last_updated_fallback("empty").unwrap();
+        assert_eq!(process(&mut processor, "a.md", content).last_updated, "");
+
+        processor.set_last_updated_fallback("epoch").unwrap();
+        assert_eq!(
+            process(&mut processor, "b.md", content).last_updated,
+            "1970-01-01T00:00:00.000Z"
+        );
+
+        processor.set_last_updated_fallback("mtime").unwrap();
+        let paper = processor
+            .process_with_mtime("c.md", content, Some(86_400_000))
+            .unwrap();
+        assert_eq!(paper.last_updated, "1970-01-02T00:00:00.000Z");
+    }
+
+    #[test]
+    fn extract_headings_outlines_every_level() {
+        let headings = extract_headings(
+            "# Title\n\n## Part *One*\n\n### Detail\n\n```\n# not a heading\n```\n\n## Part Two\n",
+        );
+        let outline: Vec<(u32, &str, &str)> = headings
+            .iter()
+            .map(|h| (h.level, h.text.as_str(), h.id.as_str()))
+            .collect();
+        assert_eq!(
+            outline,
+            [
+                (1, "Title", "title"),
+                (2, "Part One", "part-one"),
+                (3, "Detail", "detail"),
+                (2, "Part Two", "part-two"),
+            ]
+        );
+        assert!(extract_headings_json("## A\n")
+            .unwrap()
+            .contains("\"level\": 2"));
+    }
+
+    #[test]
+    fn markdown_to_text_strips_markup_and_code() {
+        let markdown = "# Heading\n\nSome *emphasis* and a [link](https://x.org).\n\n- one\n- two\n\n```rust\nfn hidden() {}\n```\n\nInline `code` stays.\n";
+        assert_eq!(
+            markdown_to_text(markdown),
+            "Heading Some emphasis and a link. one two Inline code stays."
+        );
+    }
+
+    #[test]
+    fn long_abstracts_are_truncated_at_a_word() {
+        let mut processor = PaperProcessor::new();
+        processor.set_abstract_max_chars(Some(20));
+        let content =
+            "---\ntitle: A\n---\n## Abstract\n\nA rather long abstract about many things\n";
+        let paper = process(&mut processor, "a.md", content);
+        assert_eq!(paper.abstract_text, "A rather long…");
+        assert!(paper.abstract_truncated);
+        assert_eq!(
+            paper.abstract_full.as_deref(),
+            Some("A rather long abstract about many things")
+        );
+
+        let paper = process(&mut PaperProcessor::new(), "b.md", content);
+        assert!(!paper.abstract_truncated);
+        assert_eq!(paper.abstract_full, None);
+    }
+
+    #[test]
+    fn series_are_ordered_with_neighbors() {
+        let mut processor = PaperProcessor::new();
+        for (file, order) in [("c.md", "3"), ("a.md", "1"), ("b.md", "2")] {
+            let content = format!(
+                "---\ntitle: T\nseries: Intro\nseries_order: {}\n---\n",
+                order
+            );
+            process(&mut processor, file, &content);
+        }
+        process(&mut processor, "solo.md", "---\ntitle: Solo\n---\n");
+
+        let series = processor.series();
+        assert_eq!(series.len(), 1);
+        let slugs: Vec<&str> = series[0].papers.iter().map(|e| e.slug.as_str()).collect();
+        assert_eq!(slugs, ["a", "b", "c"]);
+
+        let neighbors = processor.series_neighbors("b").unwrap();
+        assert_eq!(neighbors.previous.as_deref(), Some("a"));
+        assert_eq!(neighbors.next.as_deref(), Some("c"));
+        let neighbors = processor.series_neighbors("a").unwrap();
+        assert_eq!(
+            (neighbors.previous, neighbors.next.as_deref()),
+            (None, Some("b"))
+        );
+        let neighbors = processor.series_neighbors("solo").unwrap();
+        assert_eq!((neighbors.previous, neighbors.next), (None, None));
+        assert!(processor.series_neighbors("missing").is_none());
+    }
+
+    #[test]
+    fn unsafe_tags_are_flagged_and_slugified() {
+        let mut processor = PaperProcessor::new();
+        let paper = process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\ntags: ['CI/CD', 'Deep Learning']\n---\n",
+        );
+        assert_eq!(
+            paper.warnings,
+            ["Tag `CI/CD` is not URL-safe; its slug is `ci-cd`"]
+        );
+        let categories: Vec<(String, String)> = processor
+            .category_slugs()
+            .into_iter()
+            .map(|category| (category.display, category.slug))
+            .collect();
+        assert_eq!(
+            categories,
+            [
+                ("CI/CD".to_string(), "ci-cd".to_string()),
+                ("Deep-Learning".to_string(), "deep-learning".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn leading_h1_matching_the_title_is_stripped() {
+        let mut processor = PaperProcessor::new();
+        processor.set_strip_title_heading(true);
+        let paper = process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: Paper\n---\n# paper\n\nBody\n\n# Paper\n",
+        );
+        assert_eq!(paper.html, "<p>Body</p>\n<h1 id=\"paper\">Paper</h1>\n");
+
+        let paper = process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: Paper\n---\n# Other\n\nBody\n",
+        );
+        assert!(paper.html.starts_with("<h1 id=\"other\">Other</h1>"));
+        let paper = process(
+            &mut processor,
+            "c.md",
+            "---\ntitle: Paper\n---\nLead\n\n# Paper\n",
+        );
+        assert!(paper.html.contains("<h1 id=\"paper\">Paper</h1>"));
+    }
+
+    #[test]
+    fn non_latin_filenames_give_ascii_slugs() {
+        let slug = slug_from_filename("機械学習.md");
+        assert!(!slug.is_empty() && slug.is_ascii(), "{}", slug);
+        assert_eq!(slug, slugify(&slug));
+        assert_eq!(slug_from_filename("ünïcode title.md"), "unicode-title");
+        assert_eq!(slug_from_filename("notes.v2.md"), "notes.v2");
+        assert_eq!(slug_from_filename("papers/v1.0/intro"), "papers/v1.0/intro");
+        assert!(slug_from_filename("\u{e000}.md").starts_with("paper-"));
+    }
+
+    #[test]
+    fn only_images_without_alt_text_are_reported() {
+        let content = "---\ntitle: A\n---\n![](bare.png) ![Chart](chart.png)\n";
+        let paper = process(&mut PaperProcessor::new(), "a.md", content);
+        assert_eq!(paper.warnings, ["Images without alt text: bare.png"]);
+    }
+
+    #[test]
+    fn meta_tags_link_the_canonical_url() {
+        let mut processor = PaperProcessor::new();
+        let content = "---\ntitle: A\ncanonical: https://elsewhere.org/a\n---\n";
+        let paper = process(&mut processor, "a.md", content);
+        assert_eq!(
+            paper.canonical_url.as_deref(),
+            Some("https://elsewhere.org/a")
+        );
+        process(&mut processor, "b.md", "---\ntitle: B\n---\n");
+
+        let tags = processor.get_meta_tags("a", "https://site.org/").unwrap();
+        assert!(tags.contains("<link rel=\"canonical\" href=\"https://elsewhere.org/a\">"));
+        let tags = processor.get_meta_tags("b", "https://site.org/").unwrap();
+        assert!(tags.contains("<link rel=\"canonical\" href=\"https://site.org/papers/b\">"));
+    }
+
+    #[test]
+    fn skipped_heading_levels_are_reported() {
+        let content = "---\ntitle: A\n---\n# Top\n\n### Deep\n\n## Back\n\n### Fine\n";
+        let paper = process(&mut PaperProcessor::new(), "a.md", content);
+        assert_eq!(
+            paper.warnings,
+            ["Heading `Deep` skips from level 1 to level 3"]
+        );
+    }
+
+    #[test]
+    fn hidden_papers_are_unlisted_but_reachable() {
+        let mut processor = PaperProcessor::new();
+        let front = "tags: [site]\nauthors: [Jane Doe]\nstatus: published\n";
+        process(
+            &mut processor,
+            "about.md",
+            &format!("---\ntitle: About\nhidden: true\n{}---\n", front),
+        );
+        process(
+            &mut processor,
+            "a.md",
+            &format!("---\ntitle: A\n{}---\n", front),
+        );
+
+        let list: serde_json::Value =
+            serde_json::from_str(&processor.get_papers_list_json().unwrap()).unwrap();
+        assert_eq!(list.as_array().unwrap().len(), 1);
+        assert_eq!(list[0]["slug"], "a");
+        let tagged: Vec<&str> = processor
+            .papers_by_tag("site")
+            .iter()
+            .map(|paper| paper.slug.as_str())
+            .collect();
+        assert_eq!(tagged, ["a"]);
+        assert_eq!(processor.authors()[0].papers, ["a"]);
+
+        let about = processor.get_paper_by_slug("about").unwrap();
+        assert!(about.contains("\"hidden\":true") || about.contains("\"hidden\": true"));
+    }
+
+    #[test]
+    fn author_variants_merge_into_one_entry() {
+        let mut processor = PaperProcessor::new();
+        for (file, name) in [
+            ("a.md", "jane doe "),
+            ("b.md", "Jane  Doe"),
+            ("c.md", "jane Doe"),
+        ] {
+            let content = format!("---\ntitle: T\nauthors: ['{}']\n---\n", name);
+            process(&mut processor, file, &content);
+        }
+        let authors = processor.authors();
+        assert_eq!(authors.len(), 1);
+        assert_eq!(authors[0].name, "Jane Doe");
+        assert_eq!(authors[0].papers, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn rerender_all_applies_config_changes() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\n---\n## Part :tada:\n\n| a |\n|---|\n| 1 |\n",
+        );
+        processor.set_emoji_shortcodes(true);
+        processor.set_wrap_tables(true);
+        processor.rerender_all();
+
+        let paper = processor.paper("a").unwrap();
+        assert!(paper.html.contains("Part 🎉"));
+        assert!(paper.html.contains("<div class=\"table-wrapper\">"));
+        assert_eq!(paper.headings[0].text, "Part 🎉");
+    }
+
+    #[test]
+    fn stub_threshold_boundary() {
+        let mut processor = PaperProcessor::new();
+        processor.set_stub_word_threshold(3);
+        let paper = process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\n---\nTwo words\n\n```\nnot counted here\n```\n",
+        );
+        assert_eq!(paper.word_count, 2);
+        assert!(paper.is_stub);
+        assert_eq!(
+            paper.warnings,
+            ["Paper has 2 words, fewer than the stub threshold of 3"]
+        );
+        let paper = process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: B\n---\nThree whole words\n",
+        );
+        assert!(!paper.is_stub);
+        assert!(paper.warnings.is_empty());
+    }
+
+    #[test]
+    fn section_names_keep_document_order() {
+        let content = "---\ntitle: A\n---\n## Zeta\n\nz\n\n## Alpha\n\na\n\n## Mu\n\nm\n\n## Alpha\n\nagain\n";
+        let paper = process(&mut PaperProcessor::new(), "a.md", content);
+        assert_eq!(paper.section_names, ["zeta", "alpha", "mu"]);
+    }
+
+    #[test]
+    fn corpus_stats_total_the_listed_papers() {
+        let mut processor = PaperProcessor::new();
+        process(&mut processor, "a.md", "---\ntitle: A\ntags: [ml, nlp]\nauthors: [Jane Doe]\nlastUpdated: 2024-05-01\n---\nOne two three\n");
+        process(&mut processor, "b.md", "---\ntitle: B\ntags: [ml]\nauthors: [jane doe, Bob]\nlastUpdated: 2023-01-15\ndraft: true\n---\nFour five\n");
+        process(
+            &mut processor,
+            "c.md",
+            "---\ntitle: C\nhidden: true\nlastUpdated: 2020-01-01\n---\nHidden words here\n",
+        );
+
+        let stats = processor.corpus_stats();
+        assert_eq!((stats.papers, stats.words), (2, 5));
+        assert_eq!((stats.tags, stats.authors), (2, 2));
+        assert_eq!((stats.drafts, stats.published), (1, 1));
+        assert_eq!(stats.first_updated.as_deref(), Some("2023-01-15"));
+        assert_eq!(stats.last_updated.as_deref(), Some("2024-05-01"));
+    }
+
+    #[test]
+    fn strict_mode_rejects_explicit_slug_collisions_only() {
+        let mut processor = PaperProcessor::new();
+        processor.set_strict(true);
+        processor
+            .process("one.md", "---\ntitle: One\nslug: shared\n---\n")
+            .unwrap();
+        let error = processor
+            .process("two.md", "---\ntitle: Two\nslug: shared\n---\n")
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Slug `shared` in two.md is already used by one.md"
+        );
+
+        processor.process("a.md", "---\ntitle: A\n---\n").unwrap();
+        let paper = processor.process("ä.md", "---\ntitle: A\n---\n").unwrap();
+        assert_eq!(paper.slug, "a-2");
+    }
+
+    #[test]
+    fn render_markdown_matches_a_paper_body() {
+        let body = "## Results\n\n| a |\n|---|\n| 1 |\n\n- [x] done\n\nText[^1]\n\n[^1]: Note\n";
+        let paper = process(
+            &mut PaperProcessor::new(),
+            "a.md",
+            &format!("---\ntitle: A\n---\n{}", body),
+        );
+        assert_eq!(render_markdown(body), paper.html);
+    }
+
+    #[test]
+    fn tab_width_normalizes_nested_lists() {
+        let content = "---\ntitle: A\n---\n- outer\n\t- inner\n";
+        let mut processor = PaperProcessor::new();
+        processor.set_tab_width(Some(2));
+        let paper = process(&mut processor, "a.md", content);
+        assert_eq!(
+            paper.html,
+            "<ul>\n<li>outer\n<ul>\n<li>inner</li>\n</ul>\n</li>\n</ul>\n"
+        );
+    }
+
+    #[test]
+    fn meta_description_is_the_first_abstract_sentence() {
+        let mut processor = PaperProcessor::new();
+        let content = "---\ntitle: A\n---\n## Abstract\n\nWe study *v1.2* models. Then more!\n";
+        let paper = process(&mut processor, "a.md", content);
+        assert_eq!(paper.meta_description, "We study v1.2 models.");
+        let paper = process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: B\n---\nJust prose here? Yes.\n",
+        );
+        assert_eq!(paper.meta_description, "Just prose here?");
+
+        let tags = processor.get_meta_tags("a", "https://site.org").unwrap();
+        assert!(tags.contains("<meta name=\"description\" content=\"We study v1.2 models.\">"));
+        let list: serde_json::Value =
+            serde_json::from_str(&processor.get_papers_list_json().unwrap()).unwrap();
+        assert_eq!(list[0]["metaDescription"], "We study v1.2 models.");
+    }
+
+    #[test]
+    fn process_changed_reprocesses_only_changed_files() {
+        let file = |filename: &str, content: &str| SourceFile {
+            filename: filename.to_string(),
+            content: content.to_string(),
+            mtime: None,
+        };
+        let a = "---\ntitle: A\n---\n## One\n\nSame\n";
+        let mut processor = PaperProcessor::new();
+        process(&mut processor, "a.md", a);
+        process(&mut processor, "b.md", "---\ntitle: B\n---\nOld\n");
+        let previous: Vec<Paper> =
+            serde_json::from_str(&processor.get_papers_json().unwrap()).unwrap();
+
+        let mut processor = PaperProcessor::new();
+        let changed = processor
+            .process_changed(
+                previous,
+                &[file("a.md", a), file("b.md", "---\ntitle: B\n---\nNew\n")],
+            )
+            .unwrap();
+        assert_eq!(changed, ["b"]);
+        let slugs: Vec<&str> = processor.papers.iter().map(|p| p.slug.as_str()).collect();
+        assert_eq!(slugs, ["a", "b"]);
+        assert!(processor.paper("b").unwrap().html.contains("New"));
+        assert_eq!(processor.paper("a").unwrap().sections["one"], "Same");
+    }
+
+    #[test]
+    fn toc_source_picks_the_configured_precedence() {
+        let content = "---\ntitle: A\ntoc: [From frontmatter]\n---\n## Table of Contents\n\n1. **Manual entry**\n\n## Method\n\n## Results\n";
+        let toc = |source: &str| {
+            let mut processor = PaperProcessor::new();
+            processor.set_toc_source(source).unwrap();
+            process(&mut processor, "a.md", content).toc
+        };
+        assert_eq!(toc("manual,frontmatter"), ["Manual entry"]);
+        assert_eq!(toc("manual"), ["Manual entry"]);
+        assert_eq!(toc("frontmatter"), ["From frontmatter"]);
+        assert_eq!(toc("auto"), ["Method", "Results"]);
+        assert_eq!(toc("auto,manual"), ["Method", "Results"]);
+        assert_eq!(
+            process(&mut PaperProcessor::new(), "a.md", content).toc,
+            ["Manual entry"]
+        );
+
+        let without_manual = "---\ntitle: B\ntoc: [From frontmatter]\n---\n## Method\n";
+        let mut processor = PaperProcessor::new();
+        assert_eq!(
+            process(&mut processor, "b.md", without_manual).toc,
+            ["From frontmatter"]
+        );
+    }
+
+    #[test]
+    fn papers_in_range_include_both_boundaries() {
+        let mut processor = PaperProcessor::new();
+        for (file, date) in [
+            ("before.md", "2024-02-29T23:59:59Z"),
+            ("first.md", "2024-03-01"),
+            ("last.md", "2024-03-31T23:59:59Z"),
+            ("after.md", "2024-04-01"),
+            ("bad.md", "someday"),
+        ] {
+            let content = format!("---\ntitle: T\nlastUpdated: '{}'\n---\n", date);
+            process(&mut processor, file, &content);
+        }
+        let slugs = |start: &str, end: &str| -> Vec<String> {
+            let list: serde_json::Value =
+                serde_json::from_str(&processor.get_papers_in_range(start, end).unwrap()).unwrap();
+            let mut slugs: Vec<String> = list
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["slug"].as_str().unwrap().to_string())
+                .collect();
+            slugs.sort();
+            slugs
+        };
+        assert_eq!(slugs("2024-03-01", "2024-03-31"), ["first", "last"]);
+        assert_eq!(slugs("2024-03-31", "2024-04-01"), ["after", "last"]);
+    }
+
+    #[test]
+    fn layout_and_classes_round_trip() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\nlayout: wide\nclasses: [dark, centered]\n---\n",
+        );
+        process(&mut processor, "b.md", "---\ntitle: B\n---\n");
+
+        let papers: Vec<Paper> =
+            serde_json::from_str(&processor.get_papers_json().unwrap()).unwrap();
+        assert_eq!(papers[0].layout.as_deref(), Some("wide"));
+        assert_eq!(
+            papers[0].classes.as_deref(),
+            Some(&["dark".to_string(), "centered".to_string()][..])
+        );
+        assert_eq!(
+            (papers[1].layout.as_ref(), papers[1].classes.as_ref()),
+            (None, None)
+        );
+
+        let list: serde_json::Value =
+            serde_json::from_str(&processor.get_papers_list_json().unwrap()).unwrap();
+        assert_eq!(list[0]["layout"], "wide");
+        assert!(list[1].get("layout").is_none_or(|layout| layout.is_null()));
+    }
+
+    #[test]
+    fn sections_can_split_on_level_one() {
+        let mut processor = PaperProcessor::new();
+        processor.set_section_level(1).unwrap();
+        let content =
+            "---\ntitle: A\n---\n# Background\n\nold\n\n## Detail\n\nkept\n\n# Method\n\nnew\n";
+        let paper = process(&mut processor, "a.md", content);
+        assert_eq!(paper.section_names, ["background", "method"]);
+        assert_eq!(paper.sections["background"], "old\n\n## Detail\n\nkept");
+    }
+
+    #[test]
+    fn heading_id_function_replaces_built_in_ids() {
+        let mut processor = PaperProcessor::new();
+        processor.set_heading_id_function(|text| text.to_uppercase().replace(' ', "_"));
+        let paper = process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\n---\n## Our Method\n\n[jump](#OUR_METHOD)\n",
+        );
+        assert!(paper.html.contains("<h2 id=\"OUR_METHOD\">Our Method</h2>"));
+        assert_eq!(paper.headings[0].id, "OUR_METHOD");
+        assert!(paper.warnings.is_empty(), "{:?}", paper.warnings);
+    }
+
+    #[test]
+    fn redirect_papers_skip_rendering_but_stay_listed() {
+        let mut processor = PaperProcessor::new();
+        let paper = process(
+            &mut processor,
+            "pointer.md",
+            "---\ntitle: Pointer\nsummary: S\nredirect: https://example.com/paper\n---\n# Body\n\nNot rendered.\n",
+        );
+        assert_eq!(paper.html, "");
+        assert_eq!(
+            paper.redirect_url.as_deref(),
+            Some("https://example.com/paper")
+        );
+        let list = processor.get_papers_list_json().unwrap();
+        assert!(list.contains("\"redirectUrl\": \"https://example.com/paper\""));
+    }
+
+    #[test]
+    fn schema_version_is_exposed() {
+        let processor = PaperProcessor::new();
+        assert_eq!(processor.get_schema_version(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn heading_id_styles_over_the_same_headings() {
+        let content = "---\ntitle: A\nsummary: S\n---\n## C++ & Rust\n\n## Results\n\n## Results\n";
+        let ids = |style: &str| {
+            let mut processor = PaperProcessor::new();
+            processor.set_heading_id_style(style).unwrap();
+            let paper = process(&mut processor, "a.md", content);
+            let ids: Vec<_> = paper.headings.iter().map(|h| h.id.clone()).collect();
+            (ids, paper.html)
+        };
+
+        let (github, _) = ids("github");
+        assert_eq!(github, ["c--rust", "results", "results-1"]);
+        let (kebab, _) = ids("kebab");
+        assert_eq!(kebab, ["c++-&-rust", "results", "results"]);
+        let (none, html) = ids("none");
+        assert!(none.iter().all(String::is_empty), "{:?}", none);
+        assert!(!html.contains(" id="));
+    }
+
+    #[test]
+    fn lint_corpus_aggregates_errors_and_warnings() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\nsummary: S\n---\n## Top\n\n![](figure.png)\n\n#### Deep\n\n[[gone]]\n",
+        );
+        process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: B\nsummary: S\n---\n## Top\n\n[[a#top]]\n",
+        );
+
+        let report = processor.lint();
+        let issues: Vec<_> = report
+            .issues
+            .iter()
+            .map(|issue| (issue.severity, issue.slug.as_str(), issue.message.as_str()))
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                (
+                    Severity::Warning,
+                    "a",
+                    "Heading `Deep` skips from level 2 to level 4"
+                ),
+                (
+                    Severity::Warning,
+                    "a",
+                    "Images without alt text: figure.png"
+                ),
+                (Severity::Error, "a", "Broken link [[gone]]"),
+            ]
+        );
+        assert!(!report.ok);
+        assert_eq!((report.error_count, report.warning_count), (1, 2));
+    }
+
+    #[test]
+    fn frontmatter_summary_wins_over_the_placeholder() {
+        let mut processor = PaperProcessor::new();
+        let paper = process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\nsummary: From frontmatter\n---\n## Method\n\nBody.\n",
+        );
+        assert_eq!(paper.summary, "From frontmatter");
+
+        let paper = process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: B\nsummary: From frontmatter\n---\n## Summary\n\nFrom the section\n",
+        );
+        assert_eq!(paper.summary, "From frontmatter");
+    }
+
+    #[test]
+    fn word_frequencies_rank_prose_terms() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\nsummary: S\n---\nThe parser feeds the parser. A parser and a lexer.\n\n```\nlexer lexer lexer lexer\n```\n",
+        );
+        let terms = processor.word_frequencies("a", 2).unwrap();
+        let terms: Vec<_> = terms
+            .iter()
+            .map(|term| (term.term.as_str(), term.count))
+            .collect();
+        assert_eq!(terms, [("parser", 3), ("feeds", 1)]);
+        assert!(processor.word_frequencies("missing", 2).is_none());
+    }
+
+    #[test]
+    fn tag_suggestions_use_the_tag_vocabulary() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\nsummary: S\ntags: [rust]\n---\nParsing input with parsing combinators.\n",
+        );
+        process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: B\nsummary: S\ntags: [rust, parsing]\n---\nBody.\n",
+        );
+        process(
+            &mut processor,
+            "c.md",
+            "---\ntitle: C\nsummary: S\ntags: [cooking]\n---\nBody.\n",
+        );
+        let suggestions = processor.tag_suggestions("a", 5).unwrap();
+        assert_eq!(suggestions, ["parsing"]);
+        assert!(processor.tag_suggestions("missing", 5).is_none());
+    }
+
+    #[test]
+    fn untagged_corpora_suggest_frequent_terms() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\nsummary: S\n---\nCompilers and compilers and parsers.\n",
+        );
+        assert_eq!(
+            processor.tag_suggestions("a", 2).unwrap(),
+            ["compilers", "parsers"]
+        );
+    }
+
+    #[test]
+    fn combined_html_has_every_paper_with_unique_anchors() {
+        let mut processor = PaperProcessor::new();
+        for (file, title) in [("a.md", "Alpha"), ("b.md", "Beta")] {
+            let content = format!(
+                "---\ntitle: {}\nsummary: S\n---\n## Method\n\n[back](#method)\n",
+                title
+            );
+            process(&mut processor, file, &content);
+        }
+        process(
+            &mut processor,
+            "c.md",
+            "---\ntitle: Draft\nsummary: S\ndraft: true\n---\n## Method\n",
+        );
+
+        let html = processor.combined_html(PaperOrder::Title);
+        assert!(html.find("<h1>Alpha</h1>").unwrap() < html.find("<h1>Beta</h1>").unwrap());
+        assert!(!html.contains("Draft"));
+        assert!(html.contains("<a href=\"#a__method\">Method</a>"));
+        assert!(html.contains("<a href=\"#b__method\">back</a>"));
+
+        let mut ids: Vec<&str> = html
+            .split(" id=\"")
+            .skip(1)
+            .map(|rest| &rest[..rest.find('"').unwrap()])
+            .collect();
+        assert_eq!(ids.len(), 4);
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(ids, ["a", "a__method", "b", "b__method"]);
+    }
+
+    #[test]
+    fn comma_separated_tags_are_split() {
+        let paper = process(
+            &mut PaperProcessor::new(),
+            "a.md",
+            "---\ntitle: A\nsummary: S\ntags: machine-learning, nlp\n---\n",
+        );
+        assert_eq!(paper.tags.unwrap(), ["machine-learning", "nlp"]);
+
+        let paper = process(
+            &mut PaperProcessor::new(),
+            "b.md",
+            "---\ntitle: B\nsummary: S\ntags: single\n---\n",
+        );
+        assert_eq!(paper.tags.unwrap(), ["single"]);
+    }
+
+    #[test]
+    fn card_html_is_a_balanced_escaped_fragment() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\nsummary: Fast & **parsing** <b>now\n---\n## Abstract\n\nWe parse. Then we lex.\n",
+        );
+        let html = processor.card_html("a").unwrap();
+        assert_eq!(
+            html,
+            "<div class=\"paper-card\"><p class=\"card-summary\">Fast &amp; parsing now</p>\
+             <p class=\"card-abstract\">We parse.</p></div>"
+        );
+        assert!(roxmltree::Document::parse(&html).is_ok());
+        assert!(processor.card_html("missing").is_none());
+    }
+
+    #[test]
+    fn max_papers_caps_the_collection() {
+        let mut processor = PaperProcessor::new();
+        processor.set_max_papers(Some(2));
+        process(&mut processor, "a.md", "---\ntitle: A\nsummary: S\n---\n");
+        process(&mut processor, "b.md", "---\ntitle: B\nsummary: S\n---\n");
+
+        let error = processor
+            .process("c.md", "---\ntitle: C\nsummary: S\n---\n")
+            .unwrap_err();
+        assert!(matches!(error, ProcessError::CapacityExceeded { limit: 2 }));
+        assert_eq!(error.to_string(), "Cannot hold more than 2 papers");
+        assert_eq!(processor.papers.len(), 2);
+
+        processor.set_max_papers(None);
+        process(&mut processor, "c.md", "---\ntitle: C\nsummary: S\n---\n");
+        assert_eq!(processor.papers.len(), 3);
+    }
+
+    #[test]
+    fn captioned_images_become_numbered_figures() {
+        let paper = process(
+            &mut PaperProcessor::new(),
+            "a.md",
+            "---\ntitle: A\nsummary: S\n---\n![Model overview](model.png)\n\n![](loss.png)\n*Training loss*\n\n![](plain.png)\n",
+        );
+        let figures: Vec<_> = paper
+            .figures
+            .iter()
+            .map(|f| (f.id.as_str(), f.caption.as_str(), f.src.as_str()))
+            .collect();
+        assert_eq!(
+            figures,
+            [
+                ("figure-1", "Model overview", "model.png"),
+                ("figure-2", "Training loss", "loss.png"),
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_slugs_compare_against_a_previous_dump() {
+        let mut before = PaperProcessor::new();
+        process(
+            &mut before,
+            "kept.md",
+            "---\ntitle: Kept\nsummary: S\n---\nSame.\n",
+        );
+        process(
+            &mut before,
+            "edited.md",
+            "---\ntitle: Edited\nsummary: S\n---\nOld.\n",
+        );
+        process(
+            &mut before,
+            "gone.md",
+            "---\ntitle: Gone\nsummary: S\n---\nBye.\n",
+        );
+        let previous_json = before.get_papers_json().unwrap();
+
+        let mut after = PaperProcessor::new();
+        process(
+            &mut after,
+            "kept.md",
+            "---\ntitle: Kept\nsummary: S\n---\nSame.\n",
+        );
+        process(
+            &mut after,
+            "edited.md",
+            "---\ntitle: Edited\nsummary: S\n---\nNew.\n",
+        );
+        process(
+            &mut after,
+            "new.md",
+            "---\ntitle: New\nsummary: S\n---\nHi.\n",
+        );
+
+        let previous: Vec<Paper> = serde_json::from_str(&previous_json).unwrap();
+        let changes = after.slug_changes(&previous);
+        assert_eq!(changes.added, ["new"]);
+        assert_eq!(changes.removed, ["gone"]);
+        assert_eq!(changes.modified, ["edited"]);
+
+        let json = after.changed_slugs(&previous_json).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["added"], serde_json::json!(["new"]));
+    }
+
+    #[test]
+    fn field_aliases_map_legacy_keys() {
+        let mut processor = PaperProcessor::new();
+        processor.set_field_alias("keywords", "tags");
+        processor.set_field_alias("updated", "lastUpdated");
+        let paper = process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\nsummary: S\nkeywords: [nlp, parsing]\nupdated: 2024-03-05\n---\n",
+        );
+        assert_eq!(paper.tags.unwrap(), ["nlp", "parsing"]);
+        assert_eq!(paper.last_updated, "2024-03-05");
+
+        let paper = process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: B\nsummary: S\ntags: [kept]\nkeywords: [ignored]\n---\n",
+        );
+        assert_eq!(paper.tags.unwrap(), ["kept"]);
+    }
+
+    #[test]
+    fn max_archive_bytes_guards_processor_archives() {
+        let tar = tar_entry("a.md", octal_size(5), b"hello");
+        let mut processor = PaperProcessor::new();
+        assert_eq!(
+            processor.tar_archive_files(&tar, &[".md"]).unwrap().len(),
+            1
+        );
+
+        processor.set_max_archive_bytes(Some(1023));
+        let error = processor.tar_archive_files(&tar, &[".md"]).unwrap_err();
+        assert!(matches!(
+            error,
+            ProcessError::TooLarge {
+                size: 1024,
+                limit: 1023
+            }
+        ));
+        assert!(processor.papers.is_empty());
+    }
+
+    #[test]
+    fn heading_positions_increase_through_the_body() {
+        let paper = process(
+            &mut PaperProcessor::new(),
+            "a.md",
+            "---\ntitle: A\nsummary: S\n---\n## One\n\nSome text here.\n\n### Two\n\nMore text follows.\n\n## Three\n\nThe end.\n",
+        );
+        let positions: Vec<f64> = paper.headings.iter().map(|h| h.position).collect();
+        assert_eq!(positions.len(), 3);
+        assert_eq!(positions[0], 0.0);
+        assert!(
+            positions.windows(2).all(|pair| pair[0] < pair[1]),
+            "{:?}",
+            positions
+        );
+        assert!(positions.iter().all(|&p| (0.0..1.0).contains(&p)));
+    }
+
+    #[test]
+    fn toc_is_read_from_configured_headings() {
+        let content = "---\ntitle: A\nsummary: S\n---\n## Contents\n1. **Intro**\n2. **Method**\n\n## Intro\n\nx\n";
+        let paper = process(&mut PaperProcessor::new(), "a.md", content);
+        assert!(paper.toc.is_empty(), "{:?}", paper.toc);
+
+        let mut processor = PaperProcessor::new();
+        processor.set_toc_headings(vec![
+            "Table of Contents".to_string(),
+            "CONTENTS".to_string(),
+        ]);
+        let paper = process(&mut processor, "a.md", content);
+        assert_eq!(paper.toc, ["Intro", "Method"]);
+    }
+
+    #[test]
+    fn recent_papers_are_newest_first_and_limited() {
+        let mut processor = PaperProcessor::new();
+        for (file, date, draft) in [
+            ("old.md", "2023-01-01", false),
+            ("bad.md", "someday", false),
+            ("new.md", "2024-06-01", false),
+            ("draft.md", "2025-01-01", true),
+            ("mid.md", "2024-01-15", false),
+        ] {
+            let content = format!(
+                "---\ntitle: {}\nsummary: S\nlastUpdated: {}\ndraft: {}\n---\n",
+                file, date, draft
+            );
+            process(&mut processor, file, &content);
+        }
+        let slugs = |limit| -> Vec<String> {
+            processor
+                .recent_papers(limit)
+                .iter()
+                .map(|paper| paper.slug.clone())
+                .collect()
+        };
+        assert_eq!(slugs(2), ["new", "mid"]);
+        assert_eq!(slugs(10), ["new", "mid", "old", "bad"]);
+    }
+
+    #[test]
+    fn robots_meta_only_for_noindex_papers() {
+        let mut processor = PaperProcessor::new();
+        let paper = process(
+            &mut processor,
+            "hidden.md",
+            "---\ntitle: H\nsummary: S\nnoindex: true\n---\n",
+        );
+        assert!(paper.noindex);
+        process(
+            &mut processor,
+            "open.md",
+            "---\ntitle: O\nsummary: S\n---\n",
+        );
+
+        let robots = "<meta name=\"robots\" content=\"noindex\">";
+        let tags = processor
+            .get_meta_tags("hidden", "https://site.org/")
+            .unwrap();
+        assert!(tags.contains(robots));
+        let tags = processor
+            .get_meta_tags("open", "https://site.org/")
+            .unwrap();
+        assert!(!tags.contains(robots));
+        assert_eq!(processor.listed_papers().count(), 2);
+    }
+
+    #[test]
+    fn combined_html_namespaces_footnotes() {
+        let mut processor = PaperProcessor::new();
+        for (file, note) in [("a.md", "Alpha note"), ("b.md", "Beta note")] {
+            let content = format!(
+                "---\ntitle: {}\nsummary: S\n---\nClaim.[^1]\n\n[^1]: {}\n",
+                file, note
+            );
+            process(&mut processor, file, &content);
+        }
+        let html = processor.combined_html(PaperOrder::Slug);
+        for slug in ["a", "b"] {
+            assert!(html.contains(&format!("<a href=\"#{}__1\">1</a>", slug)));
+            assert!(html.contains(&format!(
+                "<div class=\"footnote-definition\" id=\"{}__1\">",
+                slug
+            )));
+        }
+        assert!(!html.contains("id=\"1\""));
+        let b = html.find("<article id=\"b\"").unwrap();
+        assert!(html[b..].contains("Beta note") && !html[b..].contains("Alpha note"));
+    }
+
+    #[test]
+    fn authors_sort_by_surname_unless_given_a_sort_name() {
+        let mut processor = PaperProcessor::new();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\nsummary: S\nauthors: [Adam Smith, Jane Doe]\n---\n",
+        );
+        let authors = processor.authors();
+        let names: Vec<(&str, &str)> = authors
+            .iter()
+            .map(|author| (author.name.as_str(), author.sort_name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [("Jane Doe", "Doe, Jane"), ("Adam Smith", "Smith, Adam")]
+        );
+
+        process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: B\nsummary: S\nauthors:\n  - name: Jane Doe\n    sortName: Zed, Jane\n---\n",
+        );
+        let authors = processor.authors();
+        let names: Vec<(&str, &str)> = authors
+            .iter()
+            .map(|author| (author.name.as_str(), author.sort_name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            [("Adam Smith", "Smith, Adam"), ("Jane Doe", "Zed, Jane")]
+        );
+    }
+
+    #[test]
+    fn mermaid_fences_render_as_diagrams_when_enabled() {
+        let content = "---\ntitle: A\nsummary: S\n---\n```mermaid\ngraph TD; A-->B\n```\n\n```rust\nfn main() {}\n```\n";
+        let mut processor = PaperProcessor::new();
+        processor.set_mermaid(true);
+        let paper = process(&mut processor, "a.md", content);
+        assert!(paper
+            .html
+            .contains("<div class=\"mermaid\">graph TD; A--&gt;B\n</div>"));
+        assert!(paper
+            .html
+            .contains("<pre><code class=\"language-rust\">fn main() {}"));
+        assert!(!paper.html.contains("language-mermaid"));
+
+        let paper = process(&mut PaperProcessor::new(), "a.md", content);
+        assert!(!paper.html.contains("<div class=\"mermaid\">"));
+        assert!(paper
+            .html
+            .contains("<pre><code class=\"language-mermaid\">graph TD; A--&gt;B"));
+    }
+
+    #[test]
+    fn resolved_includes_refresh_what_depends_on_the_body() {
+        let mut processor = PaperProcessor::new();
+        processor.set_stub_word_threshold(8);
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\nsummary: S\n---\n## Shared\n\n### Detail\n\nOne two three four five six.\n\n![](chart.png)\n",
+        );
+        let b = process(
+            &mut processor,
+            "b.md",
+            "---\ntitle: B\nsummary: S\n---\n## Intro\n\nSee [detail](#detail).\n\n{{include: a#shared}}\n",
+        );
+        assert!(b.is_stub);
+        assert_eq!(
+            b.warnings,
+            [
+                "Link to #detail does not match any heading",
+                "Paper has 5 words, fewer than the stub threshold of 8",
+            ]
+        );
+        processor.resolve_includes();
+
+        let b = processor.paper("b").unwrap();
+        assert!(!b.is_stub);
+        assert_eq!(b.warnings, ["Images without alt text: chart.png"]);
+        assert_eq!(b.render_warnings, b.warnings);
+        assert_eq!(b.section_names, ["intro"]);
+        assert!(b.sections["intro"].contains("### Detail"));
+        assert_eq!(b.headings[1].id, "detail");
+    }
+
+    #[test]
+    fn rerender_all_keeps_source_warnings_matching_old_render_warnings() {
+        let mut processor = PaperProcessor::new();
+        processor.set_heading_id_style("none").unwrap();
+        process(
+            &mut processor,
+            "a.md",
+            "---\ntitle: A\nsummary: S\n---\n## Top\n\n[up](#top)\n",
+        );
+        let broken = "Link to #top does not match any heading";
+        assert_eq!(processor.papers[0].render_warnings, [broken]);
+
+        // A frontmatter or include warning that happens to read the same
+        let paper = &mut processor.papers[0];
+        paper.source_warnings.push(broken.to_string());
+        paper.warnings.insert(0, broken.to_string());
+
+        processor.set_heading_id_style("kebab").unwrap();
+        processor.rerender_all();
+        let paper = &processor.papers[0];
+        assert!(paper.render_warnings.is_empty());
+        assert_eq!(paper.warnings, [broken]);
+    }
 }
@@ -0,0 +1,55 @@
+use pulldown_cmark::{Event, Tag};
+use regex::Regex;
+use serde::Serialize;
+
+/// A link that points at no paper or heading.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    /// Slug of the paper containing the link
+    pub slug: String,
+    /// The link as written, such as `[[other-paper#results]]` or `#results`
+    pub link: String,
+}
+
+/// A `[[slug]]` or `[[slug#anchor]]` reference to another paper
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossRef {
+    pub slug: String,
+    pub anchor: Option<String>,
+}
+
+impl CrossRef {
+    /// The reference as written in markdown
+    pub fn to_markdown(&self) -> String {
+        match self.anchor {
+            Some(ref anchor) => format!("[[{}#{}]]", self.slug, anchor),
+            None => format!("[[{}]]", self.slug),
+        }
+    }
+}
+
+/// Every `[[slug]]` reference in prose, outside code. A `|label` after the
+/// target is allowed and ignored.
+pub fn cross_refs(events: &[Event<'_>]) -> Vec<CrossRef> {
+    let reference = Regex::new(r"\[\[([^\[\]|#]+)(?:#([^\[\]|]+))?(?:\|[^\[\]]*)?\]\]").unwrap();
+    let mut refs = Vec::new();
+    let mut in_code_block = false;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Text(text) if !in_code_block => {
+                for captures in reference.captures_iter(text) {
+                    refs.push(CrossRef {
+                        slug: captures[1].trim().to_string(),
+                        anchor: captures.get(2).map(|m| m.as_str().trim().to_string()),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    refs
+}
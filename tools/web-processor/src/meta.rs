@@ -0,0 +1,120 @@
+use serde_json::{json, Value};
+
+use crate::dates;
+use crate::feed::{escape, paper_url};
+use crate::Paper;
+
+/// Frontmatter keys checked, in order, for a paper's publication date
+const PUBLISHED_KEYS: [&str; 3] = ["datePublished", "published", "date"];
+
+/// A schema.org `ScholarlyArticle` for `paper`. `headline`, `url`, `author`,
+/// `datePublished` and `dateModified` are always present; the publication
+/// date falls back to `lastUpdated` when frontmatter has none.
+pub fn json_ld(paper: &Paper, base_url: &str) -> Value {
+    let authors: Vec<Value> = paper
+        .authors
+        .iter()
+        .map(|author| {
+            let mut person = json!({ "@type": "Person", "name": author.name });
+            if let Some(ref affiliation) = author.affiliation {
+                person["affiliation"] = json!({ "@type": "Organization", "name": affiliation });
+            }
+            person
+        })
+        .collect();
+
+    let modified = date(&paper.last_updated);
+    let published = PUBLISHED_KEYS
+        .iter()
+        .find_map(|key| paper.extra.get(*key).and_then(Value::as_str))
+        .map(date)
+        .unwrap_or_else(|| modified.clone());
+
+    let mut article = json!({
+        "@context": "https://schema.org",
+        "@type": "ScholarlyArticle",
+        "headline": paper.title,
+        "url": paper_url(base_url, &paper.slug),
+        "author": authors,
+        "datePublished": published,
+        "dateModified": modified,
+        "description": paper.summary,
+    });
+    if let Some(ref tags) = paper.tags {
+        article["keywords"] = json!(tags.join(", "));
+    }
+    if let Some(ref image) = paper.cover_image {
+        article["image"] = json!(image);
+    }
+    article
+}
+
+/// `<meta>` and `<link>` tags for the `<head>` of a paper's page, one per line
+pub fn meta_tags(paper: &Paper, base_url: &str) -> String {
+    let canonical = paper
+        .canonical_url
+        .clone()
+        .unwrap_or_else(|| paper_url(base_url, &paper.slug));
+
+    let description = if paper.meta_description.is_empty() {
+        &paper.summary
+    } else {
+        &paper.meta_description
+    };
+
+    let mut tags = vec![
+        format!(
+            "<meta name=\"description\" content=\"{}\">",
+            escape(description)
+        ),
+        format!(
+            "<meta property=\"og:title\" content=\"{}\">",
+            escape(&paper.title)
+        ),
+        format!(
+            "<meta property=\"og:description\" content=\"{}\">",
+            escape(description)
+        ),
+        "<meta property=\"og:type\" content=\"article\">".to_string(),
+        format!(
+            "<meta property=\"og:url\" content=\"{}\">",
+            escape(&canonical)
+        ),
+    ];
+    if let Some(ref image) = paper.cover_image {
+        tags.push(format!(
+            "<meta property=\"og:image\" content=\"{}\">",
+            escape(image)
+        ));
+    }
+    if let Some(ref keywords) = paper.tags {
+        tags.push(format!(
+            "<meta name=\"keywords\" content=\"{}\">",
+            escape(&keywords.join(", "))
+        ));
+    }
+    for author in &paper.authors {
+        tags.push(format!(
+            "<meta name=\"author\" content=\"{}\">",
+            escape(&author.name)
+        ));
+    }
+    if paper.noindex {
+        tags.push("<meta name=\"robots\" content=\"noindex\">".to_string());
+    }
+    tags.push(format!(
+        "<link rel=\"canonical\" href=\"{}\">",
+        escape(&canonical)
+    ));
+
+    let mut html = tags.join("\n");
+    html.push('\n');
+    html
+}
+
+/// `value` as an RFC 3339 date-time when it parses, otherwise unchanged
+fn date(value: &str) -> String {
+    dates::parse_date(value)
+        .map(|date| dates::format_rfc3339(date.unix_seconds()))
+        .unwrap_or_else(|| value.to_string())
+}
@@ -0,0 +1,1183 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::ops::Range;
+
+use pulldown_cmark::escape::escape_html;
+use pulldown_cmark::{
+    html, CodeBlockKind, CowStr, Event, HeadingLevel, LinkType, Options, Parser, Tag,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// The markdown extensions papers are parsed with
+pub fn options() -> Options {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options
+}
+
+/// Replace tabs in the indentation of each line with spaces up to the next
+/// multiple of `width`, leaving tabs after the first other character alone
+pub fn expand_leading_tabs(markdown: &str, width: usize) -> Cow<'_, str> {
+    if width == 0 || !markdown.contains('\t') {
+        return Cow::Borrowed(markdown);
+    }
+
+    let mut output = String::with_capacity(markdown.len());
+    for line in markdown.split_inclusive('\n') {
+        let indent_len = line.len() - line.trim_start_matches([' ', '\t']).len();
+        let mut column = 0;
+        for c in line[..indent_len].chars() {
+            let spaces = if c == '\t' { width - column % width } else { 1 };
+            output.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+        }
+        output.push_str(&line[indent_len..]);
+    }
+    Cow::Owned(output)
+}
+
+/// The text and byte range of the body's first block, if it is a level 1
+/// heading
+pub fn leading_h1(markdown: &str) -> Option<(String, Range<usize>)> {
+    let mut events = Parser::new_ext(markdown, options()).into_offset_iter();
+    let (Event::Start(Tag::Heading(HeadingLevel::H1, ..)), range) = events.next()? else {
+        return None;
+    };
+
+    let mut text = String::new();
+    for (event, _) in events {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+            Event::End(Tag::Heading(..)) => break,
+            _ => {}
+        }
+    }
+    Some((text, range))
+}
+
+/// A heading in a rendered paper, with the id its HTML element carries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heading {
+    pub level: u32,
+    pub text: String,
+    pub id: String,
+    /// How far through the body's text the heading starts, from 0 to 1
+    #[serde(default)]
+    pub position: f64,
+}
+
+/// A captioned image in a paper body, for a list of figures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Figure {
+    /// `figure-1`, `figure-2`, ... in document order
+    pub id: String,
+    pub caption: String,
+    /// Image URL, as written
+    pub src: String,
+}
+
+/// Everything produced by rendering a paper body.
+#[derive(Debug, Default)]
+pub struct Rendered {
+    pub html: String,
+    pub headings: Vec<Heading>,
+    pub stats: BodyStats,
+}
+
+/// A paper body's events after every transform, before they become HTML.
+#[derive(Debug, Default)]
+pub struct BodyEvents<'a> {
+    pub events: Vec<Event<'a>>,
+    pub headings: Vec<Heading>,
+    /// Index in `events` where each of `headings` starts
+    pub heading_starts: Vec<usize>,
+    pub stats: BodyStats,
+}
+
+impl BodyEvents<'_> {
+    pub fn into_rendered(self) -> Rendered {
+        let mut html_output = String::new();
+        html::push_html(&mut html_output, self.events.into_iter());
+        Rendered {
+            html: html_output,
+            headings: self.headings,
+            stats: self.stats,
+        }
+    }
+}
+
+/// Facts about a paper body gathered from its events.
+#[derive(Debug, Default, Clone)]
+pub struct BodyStats {
+    /// `$inline$` or `$$display$$` math appears outside code
+    pub has_math: bool,
+    /// At least one fenced code block appears
+    pub has_code: bool,
+    /// Checked task list items, nested lists included
+    pub tasks_done: usize,
+    /// All task list items, nested lists included
+    pub tasks_total: usize,
+    /// URL of the first image, as written
+    pub first_image: Option<String>,
+    /// Targets of in-document links such as `[see above](#methodology)`,
+    /// without the leading `#`
+    pub anchor_links: Vec<String>,
+    /// URLs of images written with empty alt text, such as `![](chart.png)`
+    pub images_missing_alt: Vec<String>,
+    /// Words of prose, as counted in `plain_text`
+    pub word_count: usize,
+    /// Images with a caption, as found by `figures`
+    pub figures: Vec<Figure>,
+}
+
+/// Scan events for math spans, fenced code blocks, task list items, images,
+/// in-page links and words.
+pub fn scan_body(events: &[Event<'_>]) -> BodyStats {
+    // An inline span needs non-space characters just inside both dollars and
+    // no digit right after the closing one, so "$5 and $10" is not math
+    let display_math = Regex::new(r"\$\$[\s\S]+?\$\$").unwrap();
+    let inline_math = Regex::new(r"(?m)\$[^\s$](?:[^$\n]*[^\s$])?\$(?:[^0-9]|$)").unwrap();
+
+    let mut stats = BodyStats::default();
+    let mut prose = String::new();
+    let mut in_code_block = false;
+    // URL of the image being scanned and whether it has alt text yet
+    let mut open_image: Option<(&str, bool)> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                in_code_block = true;
+                if matches!(kind, CodeBlockKind::Fenced(_)) {
+                    stats.has_code = true;
+                }
+            }
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Start(Tag::Image(_, url, _)) => {
+                if stats.first_image.is_none() {
+                    stats.first_image = Some(url.to_string());
+                }
+                open_image = Some((url, false));
+            }
+            Event::End(Tag::Image(..)) => {
+                if let Some((url, false)) = open_image.take() {
+                    stats.images_missing_alt.push(url.to_string());
+                }
+            }
+            Event::Start(Tag::Link(_, dest, _)) if dest.starts_with('#') => {
+                stats.anchor_links.push(dest[1..].to_string());
+            }
+            Event::TaskListMarker(checked) => {
+                stats.tasks_total += 1;
+                if *checked {
+                    stats.tasks_done += 1;
+                }
+            }
+            Event::Text(text) if !in_code_block => {
+                if let Some((_, ref mut has_alt)) = open_image {
+                    *has_alt |= !text.trim().is_empty();
+                }
+                prose.push_str(text);
+            }
+            Event::SoftBreak | Event::HardBreak => prose.push('\n'),
+            Event::End(Tag::Paragraph) | Event::End(Tag::Item) => prose.push_str("\n\n"),
+            _ => {}
+        }
+    }
+
+    stats.has_math = display_math.is_match(&prose) || inline_math.is_match(&prose);
+    stats.word_count = plain_text(events).split_whitespace().count();
+    stats.figures = figures(events);
+    stats
+}
+
+/// Images with a caption: an `*italic*` line just after the image, on the
+/// next line or as the following paragraph, or failing that its alt text.
+/// Images with neither are left out.
+pub fn figures(events: &[Event<'_>]) -> Vec<Figure> {
+    let mut figures = Vec::new();
+    let mut idx = 0;
+
+    while idx < events.len() {
+        if let Event::Start(Tag::Image(_, src, _)) = &events[idx] {
+            let end = events[idx..]
+                .iter()
+                .position(|event| matches!(event, Event::End(Tag::Image(..))))
+                .map_or(events.len(), |len| idx + len);
+            let caption = italic_caption(events.get(end + 1..).unwrap_or_default())
+                .unwrap_or_else(|| plain_text(&events[idx + 1..end]));
+            if !caption.is_empty() {
+                figures.push(Figure {
+                    id: format!("figure-{}", figures.len() + 1),
+                    caption,
+                    src: src.to_string(),
+                });
+            }
+            idx = end;
+        }
+        idx += 1;
+    }
+
+    figures
+}
+
+/// The text of a line that is entirely `*italic*`, at the start of `events`
+/// after a line break or a paragraph break
+fn italic_caption(events: &[Event<'_>]) -> Option<String> {
+    let rest = match events {
+        [Event::SoftBreak | Event::HardBreak, rest @ ..] => rest,
+        [Event::End(Tag::Paragraph), Event::Start(Tag::Paragraph), rest @ ..] => rest,
+        _ => return None,
+    };
+    let [Event::Start(Tag::Emphasis), rest @ ..] = rest else {
+        return None;
+    };
+    let len = rest
+        .iter()
+        .position(|event| matches!(event, Event::End(Tag::Emphasis)))?;
+    matches!(rest.get(len + 1), Some(Event::End(Tag::Paragraph)))
+        .then(|| plain_text(&rest[..len]))
+        .filter(|caption| !caption.is_empty())
+}
+
+/// Derive a heading id from its text, matching Node.js marked behavior.
+pub fn heading_id(text: &str) -> String {
+    text.to_lowercase().replace(' ', "-").replace(
+        [
+            '!', '?', ':', ';', ',', '.', '"', '\'', '(', ')', '[', ']', '{', '}',
+        ],
+        "",
+    )
+}
+
+/// Derive a heading id from its text the way GitHub does: lowercased, with
+/// everything but letters, digits, `-`, `_` and spaces dropped and each space
+/// made a `-`.
+pub fn github_heading_id(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter(|&c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
+
+/// Makes a heading id from the heading's text
+pub type HeadingIdFn = dyn Fn(&str) -> String;
+
+/// Render heading tags with `id` attributes and collect the headings.
+///
+/// Ids come from `id_fn`, such as `heading_id`, and are `{prefix}__{id}`
+/// when a prefix is given, so several papers can share one page without
+/// their anchors colliding. With `unique`, repeated ids get `-1`, `-2`, ...
+/// appended as on GitHub. Without `id_fn` headings get no `id` attribute
+/// and an empty `id`. Also returns the index of each heading's opening tag
+/// in the returned events.
+pub fn inject_heading_ids<'a>(
+    events: Vec<Event<'a>>,
+    prefix: Option<&str>,
+    id_fn: Option<&HeadingIdFn>,
+    unique: bool,
+) -> (Vec<Event<'a>>, Vec<Heading>, Vec<usize>) {
+    let mut output = Vec::with_capacity(events.len());
+    let mut headings = Vec::new();
+    let mut open: Option<(usize, u32, String)> = None;
+    let mut used = HashSet::new();
+    let mut start_events = Vec::new();
+    // Characters of text so far, and where each heading starts in them
+    let mut chars = 0;
+    let mut starts = Vec::new();
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Heading(level, ..)) => {
+                // Placeholder, replaced once the heading text is known
+                open = Some((output.len(), level as u32, String::new()));
+                start_events.push(output.len());
+                output.push(Event::Html(CowStr::from("")));
+                starts.push(chars);
+            }
+            Event::End(Tag::Heading(level, ..)) => {
+                if let Some((start, level_num, text)) = open.take() {
+                    let id = match id_fn {
+                        Some(id_fn) => {
+                            let mut id = id_fn(&text);
+                            if unique && !used.insert(id.clone()) {
+                                id = (1..)
+                                    .map(|n| format!("{}-{}", id, n))
+                                    .find(|candidate| used.insert(candidate.clone()))
+                                    .unwrap();
+                            }
+                            match prefix {
+                                Some(prefix) => format!("{}__{}", prefix, id),
+                                None => id,
+                            }
+                        }
+                        None => String::new(),
+                    };
+                    let tag = if id_fn.is_some() {
+                        let mut tag = format!("<h{} id=\"", level_num);
+                        let _ = escape_html(&mut tag, &id);
+                        tag.push_str("\">");
+                        tag
+                    } else {
+                        format!("<h{}>", level_num)
+                    };
+                    output[start] = Event::Html(CowStr::from(tag));
+                    headings.push(Heading {
+                        level: level_num,
+                        text,
+                        id,
+                        position: 0.0,
+                    });
+                }
+                output.push(Event::Html(CowStr::from(format!("</h{}>\n", level as u32))));
+            }
+            Event::Text(ref text) | Event::Code(ref text) => {
+                if let Some((_, _, ref mut heading_text)) = open {
+                    heading_text.push_str(text);
+                }
+                chars += text.chars().count();
+                output.push(event);
+            }
+            other => output.push(other),
+        }
+    }
+
+    if chars > 0 {
+        for (heading, start) in headings.iter_mut().zip(starts) {
+            heading.position = start as f64 / chars as f64;
+        }
+    }
+    (output, headings, start_events)
+}
+
+/// Coalesce adjacent text events, which pulldown-cmark may split mid-word.
+pub fn merge_text<'a>(events: impl Iterator<Item = Event<'a>>) -> Vec<Event<'a>> {
+    let mut merged: Vec<Event<'a>> = Vec::new();
+    for event in events {
+        match (merged.last_mut(), event) {
+            (Some(Event::Text(previous)), Event::Text(text)) => {
+                *previous = CowStr::from(format!("{}{}", previous, text));
+            }
+            (_, event) => merged.push(event),
+        }
+    }
+    merged
+}
+
+/// Replace GitHub emoji shortcodes such as `:tada:` with their Unicode
+/// characters. Unknown shortcodes and anything inside code are left alone.
+pub fn replace_emoji_shortcodes(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let shortcode_regex = Regex::new(r":([a-z0-9_+\-]+):").unwrap();
+    let mut in_code_block = false;
+
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                in_code_block = true;
+                Event::Start(Tag::CodeBlock(kind))
+            }
+            Event::End(Tag::CodeBlock(kind)) => {
+                in_code_block = false;
+                Event::End(Tag::CodeBlock(kind))
+            }
+            Event::Text(text) if !in_code_block && text.contains(':') => {
+                let replaced = shortcode_regex.replace_all(&text, |caps: &regex::Captures| {
+                    emojis::get_by_shortcode(&caps[1])
+                        .map(|emoji| emoji.as_str().to_string())
+                        .unwrap_or_else(|| caps[0].to_string())
+                });
+                Event::Text(CowStr::from(replaced.into_owned()))
+            }
+            other => other,
+        })
+        .collect()
+}
+
+/// Wrap bare `http(s)://` URLs in prose with links. Text already inside a
+/// link, image or code is left untouched.
+pub fn autolink_urls(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let url_regex = Regex::new(r"https?://[^\s<>]+").unwrap();
+    let mut output = Vec::with_capacity(events.len());
+    let mut in_code_block = false;
+    let mut link_depth = 0usize;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Start(Tag::Link(..)) | Event::Start(Tag::Image(..)) => link_depth += 1,
+            Event::End(Tag::Link(..)) | Event::End(Tag::Image(..)) => {
+                link_depth = link_depth.saturating_sub(1)
+            }
+            Event::Text(ref text)
+                if !in_code_block && link_depth == 0 && url_regex.is_match(text) =>
+            {
+                let mut last = 0;
+                for found in url_regex.find_iter(text) {
+                    let url = trim_url(found.as_str());
+                    if found.start() > last {
+                        output.push(Event::Text(CowStr::from(
+                            text[last..found.start()].to_string(),
+                        )));
+                    }
+                    let url = CowStr::from(url.to_string());
+                    output.push(Event::Start(Tag::Link(
+                        LinkType::Autolink,
+                        url.clone(),
+                        CowStr::from(""),
+                    )));
+                    output.push(Event::Text(url.clone()));
+                    last = found.start() + url.len();
+                    output.push(Event::End(Tag::Link(
+                        LinkType::Autolink,
+                        url,
+                        CowStr::from(""),
+                    )));
+                }
+                if last < text.len() {
+                    output.push(Event::Text(CowStr::from(text[last..].to_string())));
+                }
+                continue;
+            }
+            _ => {}
+        }
+        output.push(event);
+    }
+
+    output
+}
+
+/// Link `@username` mentions through `mention_url` and `#123` issue
+/// references through `issue_url`, replacing `{user}` and `{number}`.
+/// Text in code and in existing links is left alone.
+pub fn link_references<'a>(
+    events: Vec<Event<'a>>,
+    mention_url: Option<&str>,
+    issue_url: Option<&str>,
+) -> Vec<Event<'a>> {
+    let reference_regex =
+        Regex::new(r"(^|[^\w@#/&])(@([A-Za-z0-9](?:[A-Za-z0-9-]*[A-Za-z0-9])?)|#(\d+))\b").unwrap();
+    let mut output = Vec::with_capacity(events.len());
+    let mut in_code_block = false;
+    let mut link_depth = 0usize;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Start(Tag::Link(..)) | Event::Start(Tag::Image(..)) => link_depth += 1,
+            Event::End(Tag::Link(..)) | Event::End(Tag::Image(..)) => {
+                link_depth = link_depth.saturating_sub(1)
+            }
+            Event::Text(ref text) if !in_code_block && link_depth == 0 => {
+                let mut last = 0;
+                for caps in reference_regex.captures_iter(text) {
+                    let reference = caps.get(2).unwrap();
+                    let url =
+                        match (caps.get(3), caps.get(4)) {
+                            (Some(user), _) => mention_url
+                                .map(|template| template.replace("{user}", user.as_str())),
+                            (_, Some(number)) => issue_url
+                                .map(|template| template.replace("{number}", number.as_str())),
+                            _ => None,
+                        };
+                    let Some(url) = url else { continue };
+
+                    if reference.start() > last {
+                        output.push(Event::Text(CowStr::from(
+                            text[last..reference.start()].to_string(),
+                        )));
+                    }
+                    let url = CowStr::from(url);
+                    output.push(Event::Start(Tag::Link(
+                        LinkType::Inline,
+                        url.clone(),
+                        CowStr::from(""),
+                    )));
+                    output.push(Event::Text(CowStr::from(reference.as_str().to_string())));
+                    output.push(Event::End(Tag::Link(
+                        LinkType::Inline,
+                        url,
+                        CowStr::from(""),
+                    )));
+                    last = reference.end();
+                }
+                if last > 0 {
+                    if last < text.len() {
+                        output.push(Event::Text(CowStr::from(text[last..].to_string())));
+                    }
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        output.push(event);
+    }
+
+    output
+}
+
+/// Drop trailing sentence punctuation and unbalanced closing parentheses
+/// that are almost never part of a URL written in prose.
+fn trim_url(url: &str) -> &str {
+    let mut url = url;
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"']);
+        let trimmed = if trimmed.ends_with(')')
+            && trimmed.matches(')').count() > trimmed.matches('(').count()
+        {
+            &trimmed[..trimmed.len() - 1]
+        } else {
+            trimmed
+        };
+        if trimmed.len() == url.len() {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+/// Wrap each table in `<div class="table-wrapper">` so themes can make it
+/// scroll horizontally on narrow screens.
+pub fn wrap_tables(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut output = Vec::with_capacity(events.len());
+    for event in events {
+        match event {
+            Event::Start(Tag::Table(_)) => {
+                output.push(Event::Html(CowStr::from("<div class=\"table-wrapper\">\n")));
+                output.push(event);
+            }
+            Event::End(Tag::Table(_)) => {
+                output.push(event);
+                output.push(Event::Html(CowStr::from("</div>\n")));
+            }
+            other => output.push(other),
+        }
+    }
+    output
+}
+
+/// Point `http(s)://` links at `template`, with `{url}` replaced by the
+/// percent-encoded original. Relative, in-page and other links are untouched.
+pub fn wrap_external_links<'a>(events: Vec<Event<'a>>, template: &str) -> Vec<Event<'a>> {
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::Link(kind, dest, title)) if is_external(&dest) => {
+                let dest = template.replace("{url}", &percent_encode(&dest));
+                Event::Start(Tag::Link(kind, CowStr::from(dest), title))
+            }
+            other => other,
+        })
+        .collect()
+}
+
+/// Point in-page `#anchor` links at `#{prefix}__anchor`, matching heading ids
+/// made with the same prefix, and label footnotes `{prefix}__label` so their
+/// ids and references stay apart from other papers' on the same page
+pub fn prefix_anchor_links<'a>(events: Vec<Event<'a>>, prefix: &str) -> Vec<Event<'a>> {
+    let prefixed = |label: &str| CowStr::from(format!("{}__{}", prefix, label));
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Start(Tag::Link(kind, dest, title)) if dest.starts_with('#') => {
+                let dest = format!("#{}__{}", prefix, &dest[1..]);
+                Event::Start(Tag::Link(kind, CowStr::from(dest), title))
+            }
+            Event::FootnoteReference(label) => Event::FootnoteReference(prefixed(&label)),
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                Event::Start(Tag::FootnoteDefinition(prefixed(&label)))
+            }
+            Event::End(Tag::FootnoteDefinition(label)) => {
+                Event::End(Tag::FootnoteDefinition(prefixed(&label)))
+            }
+            other => other,
+        })
+        .collect()
+}
+
+fn is_external(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// Percent-encode everything except the characters `encodeURIComponent` keeps
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'_'
+            | b'.'
+            | b'!'
+            | b'~'
+            | b'*'
+            | b'\''
+            | b'('
+            | b')' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Turn HTML written in markdown into text, so it is shown escaped.
+pub fn escape_raw_html(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    events
+        .into_iter()
+        .map(|event| match event {
+            Event::Html(html) => Event::Text(html),
+            other => other,
+        })
+        .collect()
+}
+
+/// Elements without a closing tag, which leave an HTML run balanced
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Strip scripting from HTML written in markdown with ammonia's allowlist:
+/// unsafe elements such as `<script>` and `<iframe>`, event handler
+/// attributes and `javascript:` URLs, however they are spelled or encoded,
+/// are removed. `id`, `class` and `data-*` attributes are kept on top of the
+/// allowlist.
+///
+/// pulldown-cmark splits HTML into one event per line or tag, so each run
+/// from an opening tag to the one closing it is rendered, markdown between
+/// included, and sanitized as a whole.
+pub fn sanitize_raw_html(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut sanitizer = ammonia::Builder::default();
+    sanitizer
+        .add_tags(["input"])
+        .add_tag_attributes("input", ["type", "checked", "disabled"])
+        .add_generic_attributes(["id", "class"])
+        .add_generic_attribute_prefixes(["data-"])
+        .link_rel(None);
+
+    let mut output = Vec::with_capacity(events.len());
+    let mut events = events.into_iter().peekable();
+    while let Some(event) = events.next() {
+        let Event::Html(html) = event else {
+            output.push(event);
+            continue;
+        };
+
+        // Take events up to the last HTML event that leaves the run's tags
+        // balanced, or skip to the end of the enclosing block when it doesn't
+        let mut run = vec![Event::Html(html.clone())];
+        let mut open_tags = open_tag_delta(&html);
+        let mut complete = 1;
+        let mut depth = 0usize;
+        while open_tags > 0 {
+            let Some(next) = events.peek() else {
+                break;
+            };
+            match next {
+                Event::Start(_) => depth += 1,
+                Event::End(_) if depth == 0 => break,
+                Event::End(_) => depth -= 1,
+                Event::Html(html) => open_tags += open_tag_delta(html),
+                _ => {}
+            }
+            run.push(events.next().unwrap());
+            if depth == 0 && matches!(run.last(), Some(Event::Html(_))) {
+                complete = run.len();
+            }
+        }
+        let rest = run.split_off(complete);
+
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, run.into_iter());
+        output.push(Event::Html(CowStr::from(
+            sanitizer.clean(&html).to_string(),
+        )));
+        // Events after the last complete HTML event are sanitized as runs of
+        // their own or passed through
+        output.extend(sanitize_raw_html(rest));
+    }
+    output
+}
+
+/// How many more elements `html` opens than it closes, leaving out void and
+/// self-closing elements, comments and text in quoted attribute values
+fn open_tag_delta(html: &str) -> i32 {
+    let bytes = html.as_bytes();
+    let mut delta = 0;
+    let mut idx = 0;
+    while let Some(offset) = html[idx..].find('<') {
+        idx += offset + 1;
+        if html[idx..].starts_with("!--") {
+            idx = html[idx..]
+                .find("-->")
+                .map_or(html.len(), |end| idx + end + 3);
+            continue;
+        }
+        let closing = bytes.get(idx) == Some(&b'/');
+        let name_start = idx + usize::from(closing);
+        let name_len = html[name_start..]
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+            .unwrap_or(html.len() - name_start);
+        if name_len == 0 || !bytes[name_start].is_ascii_alphabetic() {
+            continue;
+        }
+        let name = html[name_start..name_start + name_len].to_ascii_lowercase();
+
+        // Find the end of the tag, skipping `>` inside quoted values
+        let mut quote = None;
+        let mut end = name_start + name_len;
+        while end < bytes.len() {
+            match (quote, bytes[end]) {
+                (None, b'"' | b'\'') => quote = Some(bytes[end]),
+                (Some(q), c) if c == q => quote = None,
+                (None, b'>') => break,
+                _ => {}
+            }
+            end += 1;
+        }
+        let self_closing = end > 0 && bytes.get(end - 1) == Some(&b'/');
+        idx = end.min(html.len());
+
+        if closing {
+            delta -= 1;
+        } else if !self_closing && !VOID_ELEMENTS.contains(&name.as_str()) {
+            delta += 1;
+        }
+    }
+    delta
+}
+
+/// Replace each code block with the HTML returned by `render`, given the
+/// block's language (the first word of a fence's info string) and its code.
+/// Blocks for which `render` returns `None` are left to the default renderer.
+pub fn transform_code_blocks<'a>(
+    events: Vec<Event<'a>>,
+    mut render: impl FnMut(Option<&str>, &str) -> Option<String>,
+) -> Vec<Event<'a>> {
+    let mut output = Vec::with_capacity(events.len());
+    let mut open: Option<(usize, Option<String>, String)> = None;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(ref kind)) => {
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split(' ')
+                        .next()
+                        .filter(|lang| !lang.is_empty())
+                        .map(str::to_string),
+                    CodeBlockKind::Indented => None,
+                };
+                open = Some((output.len(), lang, String::new()));
+                output.push(event);
+            }
+            Event::Text(ref text) if open.is_some() => {
+                if let Some((_, _, ref mut code)) = open {
+                    code.push_str(text);
+                }
+                output.push(event);
+            }
+            Event::End(Tag::CodeBlock(_)) if open.is_some() => {
+                let (start, lang, code) = open.take().unwrap_or_default();
+                output.push(event);
+                if let Some(html) = render(lang.as_deref(), &code) {
+                    output.truncate(start);
+                    output.push(Event::Html(CowStr::from(html)));
+                }
+            }
+            other => output.push(other),
+        }
+    }
+
+    output
+}
+
+/// Turns a code block into HTML. `lang` is the first word of a fence's info
+/// string, if any, and `code` the block's unescaped text.
+///
+/// Implement this to plug in a highlighter, such as syntect on native builds.
+pub trait CodeBlockRenderer {
+    fn render(&self, lang: Option<&str>, code: &str) -> String;
+
+    /// Render with the configured `syntax_theme`, one of `SYNTAX_THEMES`.
+    /// Highlighters override this; the default ignores the theme.
+    fn render_with_theme(&self, lang: Option<&str>, code: &str, theme: Option<&str>) -> String {
+        let _ = theme;
+        self.render(lang, code)
+    }
+}
+
+/// Names of the themes in syntect's default theme set, the names
+/// `syntax_theme` accepts
+pub const SYNTAX_THEMES: &[&str] = &[
+    "InspiredGitHub",
+    "Solarized (dark)",
+    "Solarized (light)",
+    "base16-eighties.dark",
+    "base16-mocha.dark",
+    "base16-ocean.dark",
+    "base16-ocean.light",
+];
+
+/// Renders code blocks exactly as pulldown-cmark does:
+/// `<pre><code class="language-x">` with the code escaped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultCodeBlockRenderer;
+
+impl CodeBlockRenderer for DefaultCodeBlockRenderer {
+    fn render(&self, lang: Option<&str>, code: &str) -> String {
+        let mut html = code_open_tag(lang);
+        let _ = escape_html(&mut html, code);
+        html.push_str("</code></pre>\n");
+        html
+    }
+}
+
+/// Renders code blocks like `DefaultCodeBlockRenderer`, but with every line
+/// wrapped in `<span class="line" data-line="N">` for CSS gutters.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NumberedCodeBlockRenderer;
+
+impl CodeBlockRenderer for NumberedCodeBlockRenderer {
+    fn render(&self, lang: Option<&str>, code: &str) -> String {
+        let mut html = code_open_tag(lang);
+        for (idx, line) in code
+            .strip_suffix('\n')
+            .unwrap_or(code)
+            .split('\n')
+            .enumerate()
+        {
+            html.push_str(&format!("<span class=\"line\" data-line=\"{}\">", idx + 1));
+            let _ = escape_html(&mut html, line);
+            html.push_str("</span>\n");
+        }
+        html.push_str("</code></pre>\n");
+        html
+    }
+}
+
+/// `<pre><code>`, with a `language-*` class when the language is known
+fn code_open_tag(lang: Option<&str>) -> String {
+    let mut html = String::from("<pre><code");
+    if let Some(lang) = lang {
+        html.push_str(" class=\"language-");
+        let _ = escape_html(&mut html, lang);
+        html.push('"');
+    }
+    html.push('>');
+    html
+}
+
+/// Render a mermaid diagram's source for mermaid.js to pick up. The source is
+/// escaped; mermaid reads the decoded text content.
+pub fn mermaid_block(code: &str) -> String {
+    let mut html = String::from("<div class=\"mermaid\">");
+    let _ = escape_html(&mut html, code);
+    html.push_str("</div>\n");
+    html
+}
+
+/// The prose of a rendered body: text and inline code with all markup,
+/// raw HTML and code blocks dropped, and whitespace collapsed to single spaces.
+pub fn plain_text(events: &[Event<'_>]) -> String {
+    let mut text = String::new();
+    let mut in_code_block = false;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(Tag::CodeBlock(_)) => in_code_block = false,
+            Event::Text(t) | Event::Code(t) if !in_code_block => text.push_str(t),
+            // Keep words in adjacent blocks, cells and lines apart
+            Event::End(Tag::Emphasis | Tag::Strong | Tag::Strikethrough)
+            | Event::End(Tag::Link(..) | Tag::Image(..)) => {}
+            Event::SoftBreak | Event::HardBreak | Event::Rule | Event::End(_) => text.push(' '),
+            _ => {}
+        }
+    }
+
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// One parser event, summarized for debugging
+#[derive(Debug, Serialize)]
+pub struct EventSummary {
+    /// `start`, `end`, `text`, `code`, `html`, `footnoteReference`,
+    /// `softBreak`, `hardBreak`, `rule` or `taskListMarker`
+    pub kind: &'static str,
+    /// The tag started or ended, such as `heading` or `codeBlock`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<&'static str>,
+    /// The heading level, code block language, list start, link target,
+    /// footnote label or task state
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info: Option<String>,
+    /// Length in bytes of text, code and HTML
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub len: Option<usize>,
+}
+
+/// Summarize every event pulldown-cmark produces for `markdown`, before any
+/// of the processor's own transforms
+pub fn summarize_events(markdown: &str) -> Vec<EventSummary> {
+    let summary = |kind, tag, info, len| EventSummary {
+        kind,
+        tag,
+        info,
+        len,
+    };
+    Parser::new_ext(markdown, options())
+        .map(|event| match event {
+            Event::Start(tag) => {
+                let (name, info) = describe_tag(&tag);
+                summary("start", Some(name), info, None)
+            }
+            Event::End(tag) => {
+                let (name, info) = describe_tag(&tag);
+                summary("end", Some(name), info, None)
+            }
+            Event::Text(text) => summary("text", None, None, Some(text.len())),
+            Event::Code(code) => summary("code", None, None, Some(code.len())),
+            Event::Html(html) => summary("html", None, None, Some(html.len())),
+            Event::FootnoteReference(label) => {
+                summary("footnoteReference", None, Some(label.to_string()), None)
+            }
+            Event::SoftBreak => summary("softBreak", None, None, None),
+            Event::HardBreak => summary("hardBreak", None, None, None),
+            Event::Rule => summary("rule", None, None, None),
+            Event::TaskListMarker(done) => {
+                summary("taskListMarker", None, Some(done.to_string()), None)
+            }
+        })
+        .collect()
+}
+
+/// The name of `tag` and its most useful detail
+fn describe_tag(tag: &Tag<'_>) -> (&'static str, Option<String>) {
+    match tag {
+        Tag::Paragraph => ("paragraph", None),
+        Tag::Heading(level, ..) => ("heading", Some((*level as u32).to_string())),
+        Tag::BlockQuote => ("blockQuote", None),
+        Tag::CodeBlock(CodeBlockKind::Fenced(lang)) => ("codeBlock", Some(lang.to_string())),
+        Tag::CodeBlock(CodeBlockKind::Indented) => ("codeBlock", Some("indented".to_string())),
+        Tag::List(start) => ("list", start.map(|start| start.to_string())),
+        Tag::Item => ("item", None),
+        Tag::FootnoteDefinition(label) => ("footnoteDefinition", Some(label.to_string())),
+        Tag::Table(_) => ("table", None),
+        Tag::TableHead => ("tableHead", None),
+        Tag::TableRow => ("tableRow", None),
+        Tag::TableCell => ("tableCell", None),
+        Tag::Emphasis => ("emphasis", None),
+        Tag::Strong => ("strong", None),
+        Tag::Strikethrough => ("strikethrough", None),
+        Tag::Link(_, url, _) => ("link", Some(url.to_string())),
+        Tag::Image(_, url, _) => ("image", Some(url.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render<'a>(
+        markdown: &'a str,
+        transform: impl FnOnce(Vec<Event<'a>>) -> Vec<Event<'a>>,
+    ) -> String {
+        let events = merge_text(Parser::new_ext(markdown, options()));
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, transform(events).into_iter());
+        html
+    }
+
+    const FIGURE: &str = "<div class=\"figure\">\n<img src=\"a.png\" alt=\"A\">\n</div>\n";
+
+    #[test]
+    fn allowed_raw_html_is_unchanged() {
+        assert_eq!(render(FIGURE, |events| events), FIGURE);
+    }
+
+    #[test]
+    fn escaped_raw_html_is_text() {
+        let html = render(FIGURE, escape_raw_html);
+        assert!(html.starts_with("&lt;div class=&quot;figure&quot;&gt;"));
+        assert!(!html.contains("<div"));
+    }
+
+    #[test]
+    fn sanitized_raw_html_keeps_safe_markup() {
+        let html = render(FIGURE, sanitize_raw_html);
+        assert!(html.contains("<div class=\"figure\">"));
+        assert!(html.contains("<img src=\"a.png\" alt=\"A\">"));
+        assert!(html.contains("</div>"));
+    }
+
+    #[test]
+    fn sanitize_drops_script_elements() {
+        let html = render("<script>alert(1)</script>\n\ntext\n", sanitize_raw_html);
+        assert!(!html.contains("script"));
+        assert!(!html.contains("alert"));
+        assert!(html.contains("<p>text</p>"));
+    }
+
+    #[test]
+    fn sanitize_drops_handlers_on_wrapped_tags() {
+        let html = render("<div\nonclick=\"evil()\">\nhi\n</div>\n", sanitize_raw_html);
+        assert!(!html.contains("onclick"), "{}", html);
+        assert!(html.contains("<div>"));
+    }
+
+    #[test]
+    fn sanitize_drops_entity_encoded_script_urls() {
+        let html = render(
+            "<a href=\"jav&#x61;script:alert(1)\">x</a>\n",
+            sanitize_raw_html,
+        );
+        assert!(!html.contains("href"), "{}", html);
+        assert!(!html.contains("alert"));
+    }
+
+    #[test]
+    fn sanitize_drops_slash_separated_handlers() {
+        // Inside an HTML block, where pulldown-cmark passes lines through as
+        // written rather than requiring well-formed tags
+        let html = render(
+            "<div>\n<img src=\"a.png\"/onload=\"evil()\">\n<svg/onload=evil()>\n</div>\n",
+            sanitize_raw_html,
+        );
+        assert!(!html.contains("onload"), "{}", html);
+        assert!(!html.contains("evil"));
+    }
+
+    #[test]
+    fn sanitize_drops_xlink_href() {
+        let html = render(
+            "<svg><a xlink:href=\"javascript:alert(1)\">x</a></svg>\n",
+            sanitize_raw_html,
+        );
+        assert!(!html.contains("xlink"), "{}", html);
+        assert!(!html.contains("javascript"));
+    }
+
+    #[test]
+    fn sanitize_keeps_inline_markdown_inside_html() {
+        let html = render(
+            "Text <span onclick=\"x()\">*em*</span> end\n",
+            sanitize_raw_html,
+        );
+        assert_eq!(html, "<p>Text <span><em>em</em></span> end</p>\n");
+    }
+
+    #[test]
+    fn sanitize_keeps_markdown_blocks_inside_html() {
+        let html = render("<div>\n\n*em*\n\n</div>\n\n# Next\n", sanitize_raw_html);
+        assert!(
+            html.contains("<div>\n<p><em>em</em></p>\n</div>"),
+            "{}",
+            html
+        );
+        assert!(html.ends_with("<h1>Next</h1>\n"));
+    }
+
+    #[test]
+    fn emoji_shortcodes_are_replaced_outside_code() {
+        let html = render(
+            "Done :tada: :not_an_emoji: `:tada:`\n\n```\n:tada:\n```\n",
+            replace_emoji_shortcodes,
+        );
+        assert_eq!(
+            html,
+            "<p>Done 🎉 :not_an_emoji: <code>:tada:</code></p>\n<pre><code>:tada:\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn bare_urls_are_linked() {
+        let html = render("See https://example.com/a.html.\n", autolink_urls);
+        assert_eq!(
+            html,
+            "<p>See <a href=\"https://example.com/a.html\">https://example.com/a.html</a>.</p>\n"
+        );
+    }
+
+    #[test]
+    fn urls_in_links_and_code_are_not_linked() {
+        let markdown = "[https://example.com](https://example.com) `https://example.com`\n";
+        assert_eq!(
+            render(markdown, autolink_urls),
+            render(markdown, |events| events)
+        );
+    }
+
+    #[test]
+    fn tables_are_wrapped_and_other_content_is_not() {
+        let html = render("Text\n\n| a |\n|---|\n| 1 |\n", wrap_tables);
+        assert!(html.starts_with("<p>Text</p>\n<div class=\"table-wrapper\">\n<table>"));
+        assert!(html.ends_with("</table>\n</div>\n"), "{}", html);
+        assert_eq!(html.matches("table-wrapper").count(), 1);
+    }
+
+    #[test]
+    fn numbered_code_blocks_mark_each_line() {
+        let html =
+            NumberedCodeBlockRenderer.render(Some("rust"), "let a = 1;\nlet b = a < 2;\nb\n");
+        assert_eq!(
+            html,
+            "<pre><code class=\"language-rust\">\
+             <span class=\"line\" data-line=\"1\">let a = 1;</span>\n\
+             <span class=\"line\" data-line=\"2\">let b = a &lt; 2;</span>\n\
+             <span class=\"line\" data-line=\"3\">b</span>\n\
+             </code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn only_external_links_are_wrapped() {
+        let html = render(
+            "[out](https://a.org/x?y=1&z) [in](/papers/b) [top](#top)\n",
+            |events| wrap_external_links(events, "https://go.me/?u={url}"),
+        );
+        assert_eq!(
+            html,
+            "<p><a href=\"https://go.me/?u=https%3A%2F%2Fa.org%2Fx%3Fy%3D1%26z\">out</a> \
+             <a href=\"/papers/b\">in</a> <a href=\"#top\">top</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn references_are_linked_outside_code_when_configured() {
+        let markdown = "Thanks @octocat for #12, not `@code` or [#3](/x).\n";
+        let html = render(markdown, |events| {
+            link_references(
+                events,
+                Some("https://github.com/{user}"),
+                Some("https://github.com/o/r/issues/{number}"),
+            )
+        });
+        assert_eq!(
+            html,
+            "<p>Thanks <a href=\"https://github.com/octocat\">@octocat</a> for \
+             <a href=\"https://github.com/o/r/issues/12\">#12</a>, not <code>@code</code> or \
+             <a href=\"/x\">#3</a>.</p>\n"
+        );
+    }
+
+    #[test]
+    fn references_are_left_alone_by_default() {
+        let markdown = "Thanks @octocat for #12.\n";
+        assert_eq!(
+            render(markdown, |events| link_references(events, None, None)),
+            "<p>Thanks @octocat for #12.</p>\n"
+        );
+    }
+}
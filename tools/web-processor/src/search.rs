@@ -0,0 +1,293 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rust_stemmers::{Algorithm, Stemmer};
+
+use crate::Paper;
+
+/// Upper bound on the number of index terms matched by each fuzzy query
+/// token. Closest matches are kept first, so the cap only drops the weakest
+/// candidates on very large vocabularies. Once it is reached, only terms
+/// closer than the farthest kept one are considered, and distances are
+/// given up on as soon as they cannot be.
+const MAX_FUZZY_CANDIDATES: usize = 64;
+
+const TITLE_WEIGHT: f64 = 3.0;
+const TAG_WEIGHT: f64 = 2.0;
+const BODY_WEIGHT: f64 = 1.0;
+
+/// Common English words that carry little meaning in a query.
+const STOPWORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "an", "and", "any", "are", "as", "at", "be", "been",
+    "but", "by", "can", "could", "do", "does", "for", "from", "had", "has", "have", "he", "her",
+    "his", "how", "i", "if", "in", "into", "is", "it", "its", "may", "more", "most", "no", "not",
+    "of", "on", "or", "other", "our", "over", "she", "should", "so", "such", "than", "that", "the",
+    "their", "them", "then", "there", "these", "they", "this", "those", "through", "to", "under",
+    "up", "use", "was", "we", "were", "what", "when", "where", "which", "while", "who", "will",
+    "with", "would", "you", "your",
+];
+
+/// How text is normalized into index terms.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenizerOptions {
+    /// Drop common English stopwords
+    pub stopwords: bool,
+    /// Reduce terms to their Porter stem, so "models" matches "model"
+    pub stemming: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Posting {
+    paper: usize,
+    weight: f64,
+}
+
+/// Inverted index from normalized terms to the papers containing them.
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    terms: BTreeMap<String, Vec<Posting>>,
+    options: TokenizerOptions,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SearchHit {
+    pub slug: String,
+    pub title: String,
+    pub score: f64,
+}
+
+impl SearchIndex {
+    pub fn build(papers: &[Paper], options: TokenizerOptions) -> SearchIndex {
+        let mut terms: BTreeMap<String, HashMap<usize, f64>> = BTreeMap::new();
+
+        for (idx, paper) in papers.iter().enumerate() {
+            if paper.hidden {
+                continue;
+            }
+            let mut add = |text: &str, weight: f64| {
+                for token in tokenize(text, options) {
+                    *terms.entry(token).or_default().entry(idx).or_default() += weight;
+                }
+            };
+
+            add(&paper.title, TITLE_WEIGHT);
+            if let Some(ref tags) = paper.tags {
+                for tag in tags {
+                    add(tag, TAG_WEIGHT);
+                }
+            }
+            add(&paper.summary, BODY_WEIGHT);
+            add(&paper.content, BODY_WEIGHT);
+        }
+
+        let terms = terms
+            .into_iter()
+            .map(|(term, postings)| {
+                let mut postings: Vec<Posting> = postings
+                    .into_iter()
+                    .map(|(paper, weight)| Posting { paper, weight })
+                    .collect();
+                postings.sort_by_key(|p| p.paper);
+                (term, postings)
+            })
+            .collect();
+
+        SearchIndex { terms, options }
+    }
+
+    /// Match every query token against index terms containing it as a substring.
+    pub fn search(&self, papers: &[Paper], query: &str) -> Vec<SearchHit> {
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+
+        for token in tokenize(query, self.options) {
+            for (term, postings) in &self.terms {
+                if term.contains(&token) {
+                    for posting in postings {
+                        *scores.entry(posting.paper).or_default() += posting.weight;
+                    }
+                }
+            }
+        }
+
+        Self::collect_hits(papers, scores)
+    }
+
+    /// Match every query token against index terms within `max_distance`
+    /// Levenshtein edits (insertions, deletions or substitutions, counted per
+    /// character). A distance of 0 only accepts exact term matches. Each match
+    /// contributes `weight / (1 + distance)`, so closer terms score higher.
+    pub fn search_fuzzy(
+        &self,
+        papers: &[Paper],
+        query: &str,
+        max_distance: usize,
+    ) -> Vec<SearchHit> {
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+
+        for token in tokenize(query, self.options) {
+            let token_len = token.chars().count();
+            let mut candidates: Vec<(usize, &Vec<Posting>)> = Vec::new();
+            // Farthest distance still worth computing
+            let mut limit = max_distance;
+            for (term, postings) in &self.terms {
+                if term.chars().count().abs_diff(token_len) > limit {
+                    continue;
+                }
+                let Some(distance) = levenshtein_within(&token, term, limit) else {
+                    continue;
+                };
+                candidates.push((distance, postings));
+                if candidates.len() >= MAX_FUZZY_CANDIDATES {
+                    // Stable, so earlier terms win ties as before
+                    candidates.sort_by_key(|(distance, _)| *distance);
+                    candidates.truncate(MAX_FUZZY_CANDIDATES);
+                    match candidates[MAX_FUZZY_CANDIDATES - 1].0.checked_sub(1) {
+                        Some(closer) => limit = closer,
+                        None => break,
+                    }
+                }
+            }
+            candidates.sort_by_key(|(distance, _)| *distance);
+
+            for (distance, postings) in candidates {
+                for posting in postings {
+                    *scores.entry(posting.paper).or_default() +=
+                        posting.weight / (1.0 + distance as f64);
+                }
+            }
+        }
+
+        Self::collect_hits(papers, scores)
+    }
+
+    fn collect_hits(papers: &[Paper], scores: HashMap<usize, f64>) -> Vec<SearchHit> {
+        let mut hits: Vec<SearchHit> = scores
+            .into_iter()
+            .filter_map(|(idx, score)| {
+                papers.get(idx).map(|paper| SearchHit {
+                    slug: paper.slug.clone(),
+                    title: paper.title.clone(),
+                    score,
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.slug.cmp(&b.slug))
+        });
+        hits
+    }
+}
+
+/// Split text into lowercase alphanumeric terms, normalized per `options`.
+pub fn tokenize(text: &str, options: TokenizerOptions) -> Vec<String> {
+    let stemmer = options
+        .stemming
+        .then(|| Stemmer::create(Algorithm::English));
+
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .filter(|word| !(options.stopwords && STOPWORDS.contains(&word.as_str())))
+        .map(|word| match &stemmer {
+            Some(stemmer) => stemmer.stem(&word).into_owned(),
+            None => word,
+        })
+        .collect()
+}
+
+/// A term and how often it occurs
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct TermCount {
+    pub term: String,
+    pub count: usize,
+}
+
+/// The `top_n` most frequent terms of `text`, most frequent first and then
+/// alphabetically. Stopwords, numbers and single characters are skipped.
+pub fn term_frequencies(text: &str, top_n: usize) -> Vec<TermCount> {
+    let options = TokenizerOptions {
+        stopwords: true,
+        stemming: false,
+    };
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for term in tokenize(text, options) {
+        if term.chars().count() > 1 && !term.chars().all(|c| c.is_numeric()) {
+            *counts.entry(term).or_default() += 1;
+        }
+    }
+
+    let mut terms: Vec<TermCount> = counts
+        .into_iter()
+        .map(|(term, count)| TermCount { term, count })
+        .collect();
+    terms.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.term.cmp(&b.term)));
+    terms.truncate(top_n);
+    terms
+}
+
+/// The Levenshtein distance between `a` and `b` if it is at most `limit`.
+/// Stops early once every alignment is already farther than `limit`.
+fn levenshtein_within(a: &str, b: &str, limit: usize) -> Option<usize> {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        if current.iter().all(|&distance| distance > limit) {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    Some(previous[b.len()]).filter(|&distance| distance <= limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEM: TokenizerOptions = TokenizerOptions {
+        stopwords: false,
+        stemming: true,
+    };
+
+    #[test]
+    fn stemming_normalizes_plurals() {
+        assert_eq!(tokenize("models", STEM), tokenize("model", STEM));
+        assert_eq!(tokenize("models", TokenizerOptions::default()), ["models"]);
+    }
+
+    #[test]
+    fn stopwords_are_dropped_when_enabled() {
+        let options = TokenizerOptions {
+            stopwords: true,
+            stemming: false,
+        };
+        assert_eq!(
+            tokenize("The learning of models", options),
+            ["learning", "models"]
+        );
+        assert_eq!(
+            tokenize("The learning of models", TokenizerOptions::default()),
+            ["the", "learning", "of", "models"]
+        );
+    }
+
+    #[test]
+    fn levenshtein_within_gives_up_past_the_limit() {
+        assert_eq!(levenshtein_within("learing", "learning", 2), Some(1));
+        assert_eq!(levenshtein_within("model", "model", 0), Some(0));
+        assert_eq!(levenshtein_within("kitten", "sitting", 3), Some(3));
+        assert_eq!(levenshtein_within("kitten", "sitting", 2), None);
+        assert_eq!(levenshtein_within("abc", "xyz", 1), None);
+    }
+}